@@ -1,8 +1,36 @@
-use super::{Map, Monster, Point, Position, RunState, Viewshed, WantsToMelee};
+use super::ranged_combat_system::line_of_fire;
+use super::status_system::stumble_direction;
+use super::{
+    Behaviour, BehaviourKind, CombatStats, Door, EntityMoved, GameLog, Map, Monster, Name,
+    PlayerMemory, Point, Position, RangedWeapon, RunState, StatusEffects, StatusKind, Viewshed,
+    WantsToMelee, WantsToOpenDoor, WantsToShoot, ALERT_RADIUS, COWARD_FLEE_PERCENT,
+    FLEE_MAP_WEIGHT, KITE_DISTANCE, MONSTER_MEMORY_TURNS,
+};
 use bracket_lib::prelude::*;
 use specs::prelude::*;
+use std::collections::VecDeque;
 
-pub struct MonsterAI {}
+// What a monster settles on doing with its turn
+enum Action {
+    Melee,
+    Shoot,
+    // The next tile along the way, by index
+    Move(usize),
+    Wait,
+}
+
+// How monsters find their way to, and away from, the player
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Pathing {
+    // A search of its own for every monster, every turn
+    AStar,
+    // Flow maps built once a turn and shared by all of them
+    FlowMaps,
+}
+
+pub struct MonsterAI {
+    pub pathing: Pathing,
+}
 
 impl<'a> System<'a> for MonsterAI {
     type SystemData = (
@@ -15,11 +43,23 @@ impl<'a> System<'a> for MonsterAI {
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
         ReadExpect<'a, RunState>,
+        ReadStorage<'a, StatusEffects>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadStorage<'a, Door>,
+        WriteStorage<'a, WantsToOpenDoor>,
+        WriteStorage<'a, EntityMoved>,
+        ReadStorage<'a, RangedWeapon>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Behaviour>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, PlayerMemory>,
+        ReadStorage<'a, Name>,
+        WriteExpect<'a, GameLog>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            map,
+            mut map,
             player_pos,
             player_entity,
             entities,
@@ -28,40 +68,398 @@ impl<'a> System<'a> for MonsterAI {
             mut position,
             mut wants_to_melee,
             runstate,
+            statuses,
+            mut rng,
+            doors,
+            mut wants_to_open,
+            mut entity_moved,
+            ranged_weapons,
+            mut wants_to_shoot,
+            behaviours,
+            combat_stats,
+            mut memories,
+            names,
+            mut gamelog,
         ) = data;
 
-        if *runstate != RunState::MonsterTurn {
-            return;
-        }
+        let extra_action = match *runstate {
+            RunState::MonsterTurn => false,
+            RunState::HastedMonsterTurn => true,
+            _ => return,
+        };
+
+        let flow_maps = match self.pathing {
+            Pathing::FlowMaps => {
+                let monsters: Vec<Point> = (&monster, &position)
+                    .join()
+                    .map(|(_monster, pos)| Point::new(pos.x, pos.y))
+                    .collect();
+                Some(FlowMaps::new(&mut map, *player_pos, &monsters))
+            }
+            Pathing::AStar => None,
+        };
+
+        // Monsters that spotted the player this turn, and where they stand
+        let mut alerts: Vec<(Entity, Point)> = Vec::new();
 
         for (entity, mut viewshed, _monster, mut pos) in
             (&entities, &mut viewshed, &monster, &mut position).join()
         {
-            let distance =
-                DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
-            if distance < 1.5 {
-                wants_to_melee
-                    .insert(
-                        entity,
-                        WantsToMelee {
-                            target: *player_entity,
-                        },
-                    )
-                    .expect("Unable to insert attack");
-                return;
+            let status = statuses.get(entity);
+            if matches!(status, Some(s) if s.has(StatusKind::Sleep)) {
+                continue;
             }
-            if viewshed.visible_tiles.contains(&*player_pos) {
-                let path = a_star_search(
-                    map.xy_idx(pos.x, pos.y) as i32,
-                    map.xy_idx(player_pos.x, player_pos.y) as i32,
-                    &*map,
+            if extra_action && !matches!(status, Some(s) if s.has(StatusKind::Haste)) {
+                continue;
+            }
+            let held = matches!(status, Some(s) if s.has(StatusKind::Held));
+            let here = Point::new(pos.x, pos.y);
+
+            let action = if matches!(status, Some(s) if s.has(StatusKind::Confusion)) {
+                // The player does not block their tile, so a stumble into
+                // them goes nowhere rather than sharing it
+                let (delta_x, delta_y) = stumble_direction(&mut rng);
+                let (x, y) = (pos.x + delta_x, pos.y + delta_y);
+                if x >= 0
+                    && x < map.width
+                    && y >= 0
+                    && y < map.height
+                    && (x, y) != (player_pos.x, player_pos.y)
+                    && !map.squeezes_between_walls(pos.x, pos.y, delta_x, delta_y)
+                    && closed_door_at(&map, &doors, x, y).is_none()
+                {
+                    Action::Move(map.xy_idx(x, y))
+                } else {
+                    Action::Wait
+                }
+            } else {
+                let distance = DistanceAlg::Pythagoras.distance2d(here, *player_pos);
+                let adjacent = distance < 1.5
+                    && !map.squeezes_between_walls(
+                        here.x,
+                        here.y,
+                        player_pos.x - here.x,
+                        player_pos.y - here.y,
+                    );
+                let sees_player = viewshed.visible_tiles.contains(&*player_pos);
+                let can_shoot = sees_player
+                    && matches!(ranged_weapons.get(entity), Some(weapon) if distance <= weapon.range as f32)
+                    && line_of_fire(&map, here, *player_pos) == *player_pos;
+                let badly_hurt = matches!(
+                    combat_stats.get(entity),
+                    Some(stats) if stats.hp * 100 <= stats.max_hp * COWARD_FLEE_PERCENT
                 );
-                if path.success && path.steps.len() > 1 {
-                    pos.x = path.steps[1] as i32 % map.width;
-                    pos.y = path.steps[1] as i32 / map.width;
+                let kind = match behaviours.get(entity) {
+                    Some(behaviour) => behaviour.kind,
+                    None => BehaviourKind::Chaser,
+                };
+
+                // Seeing the player refreshes the memory of them, and a
+                // monster that was not already hunting calls out to others.
+                // Out of sight, the memory fades a turn at a time.
+                let hunting =
+                    matches!(memories.get(entity), Some(memory) if memory.last_seen.is_some());
+                if sees_player {
+                    if !hunting {
+                        alerts.push((entity, here));
+                    }
+                    memories
+                        .insert(
+                            entity,
+                            PlayerMemory {
+                                last_seen: Some(*player_pos),
+                                turns: MONSTER_MEMORY_TURNS,
+                            },
+                        )
+                        .expect("Unable to insert memory");
+                } else if let Some(memory) = memories.get_mut(entity).filter(|_| hunting) {
+                    memory.turns -= 1;
+                    if memory.turns <= 0 || memory.last_seen == Some(here) {
+                        memory.last_seen = None;
+                    }
+                }
+                let memory = memories.get(entity);
+                let knows_where =
+                    sees_player || matches!(memory, Some(memory) if memory.last_seen.is_some());
+
+                let fight = if adjacent {
+                    Action::Melee
+                } else if can_shoot {
+                    Action::Shoot
+                } else {
+                    Action::Wait
+                };
+                match kind {
+                    // A hurt coward keeps running for as long as it knows
+                    // where the player is, rather than heading back to them
+                    BehaviourKind::Coward if badly_hurt && knows_where => {
+                        run_away(&map, flow_maps.as_ref(), here, *player_pos).unwrap_or(fight)
+                    }
+                    BehaviourKind::Kiter if sees_player && distance < KITE_DISTANCE => {
+                        run_away(&map, flow_maps.as_ref(), here, *player_pos).unwrap_or(fight)
+                    }
+                    BehaviourKind::Guard => fight,
+                    // Hunt down where the player was last seen, and once
+                    // that trail goes cold, go looking for them
+                    _ if !sees_player => match memory {
+                        Some(PlayerMemory {
+                            last_seen: Some(target),
+                            ..
+                        }) => chase(&map, flow_maps.as_ref(), here, *target, *player_pos),
+                        Some(_) => wander(&map, &doors, here, &mut rng),
+                        None if kind == BehaviourKind::Patroller => {
+                            wander(&map, &doors, here, &mut rng)
+                        }
+                        None => Action::Wait,
+                    },
+                    _ => match fight {
+                        Action::Wait if sees_player => {
+                            chase(&map, flow_maps.as_ref(), here, *player_pos, *player_pos)
+                        }
+                        fight => fight,
+                    },
+                }
+            };
+
+            match action {
+                Action::Melee => {
+                    wants_to_melee
+                        .insert(
+                            entity,
+                            WantsToMelee {
+                                target: *player_entity,
+                            },
+                        )
+                        .expect("Unable to insert attack");
+                }
+                Action::Shoot => {
+                    wants_to_shoot
+                        .insert(
+                            entity,
+                            WantsToShoot {
+                                target: *player_pos,
+                            },
+                        )
+                        .expect("Unable to insert intent");
+                }
+                // Caught in a trap, a monster can only fight what comes
+                // within reach
+                Action::Move(_) if held => {}
+                Action::Move(step) => {
+                    // A closed door on the way takes a turn to open
+                    let (x, y) = map.idx_xy(step);
+                    if let Some(door) = closed_door_at(&map, &doors, x as i32, y as i32) {
+                        wants_to_open
+                            .insert(entity, WantsToOpenDoor { door })
+                            .expect("Unable to insert intent");
+                        continue;
+                    }
+
+                    // Another monster may have taken the tile this turn
+                    if map.blocked[x][y] {
+                        continue;
+                    }
+                    map.blocked[pos.x as usize][pos.y as usize] = false;
+                    map.blocked[x][y] = true;
+                    pos.x = x as i32;
+                    pos.y = y as i32;
                     viewshed.dirty = true;
+                    entity_moved
+                        .insert(entity, EntityMoved {})
+                        .expect("Unable to insert marker");
+                }
+                Action::Wait => {}
+            }
+        }
+
+        // Anything within earshot of a shout starts hunting too
+        for (shouter, origin) in alerts {
+            if map.visible_tiles[origin.x as usize][origin.y as usize] {
+                if let Some(name) = names.get(shouter) {
+                    gamelog
+                        .entries
+                        .push(format!("The {} shouts an alarm!", name.name));
+                }
+            }
+            for (entity, _monster, pos) in (&entities, &monster, &position).join() {
+                let distance = DistanceAlg::Pythagoras.distance2d(origin, Point::new(pos.x, pos.y));
+                let hunting =
+                    matches!(memories.get(entity), Some(memory) if memory.last_seen.is_some());
+                if entity != shouter && distance <= ALERT_RADIUS && !hunting {
+                    memories
+                        .insert(
+                            entity,
+                            PlayerMemory {
+                                last_seen: Some(*player_pos),
+                                turns: MONSTER_MEMORY_TURNS,
+                            },
+                        )
+                        .expect("Unable to insert memory");
                 }
             }
         }
     }
 }
+
+// Distances to the player over the whole level, and the map for running
+// away from them, built once a turn for every monster to share
+struct FlowMaps {
+    toward_player: DijkstraMap,
+    away_from_player: DijkstraMap,
+}
+
+impl FlowMaps {
+    // The monsters themselves are left out of the way while the maps are
+    // built, as most of them will have moved on by the time anyone follows
+    fn new(map: &mut Map, player: Point, monsters: &[Point]) -> FlowMaps {
+        let blocked: Vec<bool> = monsters
+            .iter()
+            .map(|pos| {
+                let was_blocked = map.blocked[pos.x as usize][pos.y as usize];
+                map.blocked[pos.x as usize][pos.y as usize] = false;
+                was_blocked
+            })
+            .collect();
+
+        let start = map.xy_idx(player.x, player.y);
+        let toward_player = DijkstraMap::new(
+            map.width,
+            map.height,
+            &[start],
+            &*map,
+            map.dijkstra_max_depth(),
+        );
+
+        // Fleeing runs downhill on the scaled distances, settled again so
+        // that every tile is at most a step's cost above its lowest neighbour
+        let mut away_from_player =
+            DijkstraMap::new_empty(map.width, map.height, map.dijkstra_max_depth());
+        let mut open = VecDeque::new();
+        for (idx, distance) in toward_player.map.iter().enumerate() {
+            if *distance < f32::MAX {
+                away_from_player.map[idx] = distance * FLEE_MAP_WEIGHT;
+                open.push_back(idx);
+            }
+        }
+        while let Some(idx) = open.pop_front() {
+            for (exit, cost) in map.get_available_exits(idx) {
+                if away_from_player.map[idx] + cost < away_from_player.map[exit] {
+                    away_from_player.map[exit] = away_from_player.map[idx] + cost;
+                    open.push_back(exit);
+                }
+            }
+        }
+
+        for (pos, was_blocked) in monsters.iter().zip(blocked) {
+            map.blocked[pos.x as usize][pos.y as usize] = was_blocked;
+        }
+
+        FlowMaps {
+            toward_player,
+            away_from_player,
+        }
+    }
+
+    // Follows the distances down toward the player, stopping short of the
+    // player's own tile, the only one at no distance at all
+    fn approach(&self, map: &Map, from: Point) -> Action {
+        let idx = map.xy_idx(from.x, from.y);
+        match DijkstraMap::find_lowest_exit(&self.toward_player, idx, map) {
+            Some(next)
+                if self.toward_player.map[next] < self.toward_player.map[idx]
+                    && self.toward_player.map[next] > 0.0 =>
+            {
+                Action::Move(next)
+            }
+            _ => Action::Wait,
+        }
+    }
+
+    fn flee(&self, map: &Map, from: Point) -> Option<Action> {
+        let idx = map.xy_idx(from.x, from.y);
+        match DijkstraMap::find_lowest_exit(&self.away_from_player, idx, map) {
+            Some(next) if self.away_from_player.map[next] < self.away_from_player.map[idx] => {
+                Some(Action::Move(next))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Heads for `target`, down the shared flow map when that is where the player
+// stands
+fn chase(
+    map: &Map,
+    flow_maps: Option<&FlowMaps>,
+    from: Point,
+    target: Point,
+    player: Point,
+) -> Action {
+    match flow_maps {
+        Some(flow_maps) if target == player => flow_maps.approach(map, from),
+        _ => approach(map, from, target),
+    }
+}
+
+fn run_away(map: &Map, flow_maps: Option<&FlowMaps>, from: Point, player: Point) -> Option<Action> {
+    match flow_maps {
+        Some(flow_maps) => flow_maps.flee(map, from),
+        None => flee(map, from, player),
+    }
+}
+
+// Heads along the shortest path to `target`
+fn approach(map: &Map, from: Point, target: Point) -> Action {
+    let path = a_star_search(
+        map.xy_idx(from.x, from.y) as i32,
+        map.xy_idx(target.x, target.y) as i32,
+        map,
+    );
+    if !path.success || path.steps.len() < 2 {
+        return Action::Wait;
+    }
+    Action::Move(path.steps[1])
+}
+
+// Steps to whichever neighbouring tile gets furthest from `threat`, if any
+// of them is further than where it stands now
+fn flee(map: &Map, from: Point, threat: Point) -> Option<Action> {
+    let mut best = (None, DistanceAlg::Pythagoras.distance2d(from, threat));
+    for (idx, _cost) in map.get_available_exits(map.xy_idx(from.x, from.y)) {
+        let (x, y) = map.idx_xy(idx);
+        let distance = DistanceAlg::Pythagoras.distance2d(Point::new(x, y), threat);
+        if distance > best.1 {
+            best = (Some(idx), distance);
+        }
+    }
+    best.0.map(Action::Move)
+}
+
+// A random step, leaving closed doors alone
+fn wander(
+    map: &Map,
+    doors: &ReadStorage<Door>,
+    from: Point,
+    rng: &mut RandomNumberGenerator,
+) -> Action {
+    let mut exits: Vec<usize> = map
+        .get_available_exits(map.xy_idx(from.x, from.y))
+        .iter()
+        .map(|(idx, _cost)| *idx)
+        .collect();
+    exits.retain(|idx| {
+        let (x, y) = map.idx_xy(*idx);
+        closed_door_at(map, doors, x as i32, y as i32).is_none()
+    });
+    if exits.is_empty() {
+        return Action::Wait;
+    }
+    Action::Move(exits[rng.range(0, exits.len() as i32) as usize])
+}
+
+fn closed_door_at(map: &Map, doors: &ReadStorage<Door>, x: i32, y: i32) -> Option<Entity> {
+    map.tile_content[x as usize][y as usize]
+        .iter()
+        .find(|entity| matches!(doors.get(**entity), Some(door) if !door.open))
+        .copied()
+}
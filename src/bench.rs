@@ -0,0 +1,125 @@
+use super::ai::{MonsterAI, Pathing};
+use super::generator;
+use super::map_builders::BuilderKind;
+use super::raws::RawMaster;
+use super::{
+    DoorSystem, EntityMoved, Map, MapIndexingSystem, Monster, PlayerMemory, Point, RunState, State,
+    VisibilitySystem, WantsToMelee, WantsToShoot, MONSTER_MEMORY_TURNS,
+};
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+use std::time::{Duration, Instant};
+
+const BENCH_TURNS: u32 = 200;
+
+// Crowds the first level with `monsters` monsters that all know where the
+// player is, and times their turns with each way of pathing in turn
+pub fn run(seed: u64, monsters: usize, raws: &RawMaster, map_builder: Option<BuilderKind>) {
+    for pathing in [Pathing::AStar, Pathing::FlowMaps].iter() {
+        let (crowd, elapsed) = time_turns(seed, monsters, raws, map_builder, *pathing);
+        println!(
+            "{:?}: {} monsters, {} turns, {:.3} ms per turn",
+            pathing,
+            crowd,
+            BENCH_TURNS,
+            elapsed.as_secs_f64() * 1000.0 / BENCH_TURNS as f64
+        );
+    }
+}
+
+// Only MonsterAI is timed. Doors, sight and the map index are kept up to
+// date between turns, but nothing is fought over, so every run plays out on
+// the same level for as long as it takes.
+fn time_turns(
+    seed: u64,
+    monsters: usize,
+    raws: &RawMaster,
+    map_builder: Option<BuilderKind>,
+    pathing: Pathing,
+) -> (usize, Duration) {
+    let mut gs = State::new(seed, raws.clone(), map_builder);
+    gs.saves_enabled = false;
+    let crowd = crowd(&mut gs.ecs, monsters);
+
+    let mut ai = MonsterAI { pathing };
+    let mut elapsed = Duration::default();
+    for _ in 0..BENCH_TURNS {
+        alert_everyone(&mut gs.ecs);
+        gs.ecs.insert(RunState::MonsterTurn);
+        let start = Instant::now();
+        ai.run_now(&gs.ecs);
+        elapsed += start.elapsed();
+
+        gs.ecs.write_storage::<WantsToMelee>().clear();
+        gs.ecs.write_storage::<WantsToShoot>().clear();
+        gs.ecs.write_storage::<EntityMoved>().clear();
+        let mut doors = DoorSystem {};
+        doors.run_now(&gs.ecs);
+        let mut map_index = MapIndexingSystem {};
+        map_index.run_now(&gs.ecs);
+        let mut vis = VisibilitySystem {};
+        vis.run_now(&gs.ecs);
+        gs.ecs.maintain();
+    }
+
+    (crowd, elapsed)
+}
+
+// Tops the level up to `count` monsters on open floor the player can be
+// reached from, and returns how many there are
+fn crowd(ecs: &mut World, count: usize) -> usize {
+    let mut spawns = Vec::new();
+    let existing = ecs.read_storage::<Monster>().join().count();
+    {
+        let player = *ecs.fetch::<Point>();
+        let map = ecs.fetch::<Map>();
+        let raws = ecs.fetch::<RawMaster>();
+        let monster_table = raws.monster_table();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+
+        let mut free_tiles: Vec<(i32, i32)> = map
+            .reachable_tiles((player.x, player.y), &[])
+            .into_iter()
+            .filter(|&(x, y)| !map.blocked[x as usize][y as usize])
+            .filter(|&(x, y)| (x, y) != (player.x, player.y))
+            .collect();
+        for _i in existing..count {
+            if free_tiles.is_empty() {
+                break;
+            }
+            let (x, y) = free_tiles.remove(rng.range(0, free_tiles.len() as i32) as usize);
+            let monster = monster_table
+                .roll(&mut rng, map.depth)
+                .and_then(|name| raws.monster(name));
+            if let Some(monster) = monster {
+                spawns.push((x, y, monster.clone()));
+            }
+        }
+    }
+
+    let depth = ecs.fetch::<Map>().depth;
+    for (x, y, monster) in spawns.iter() {
+        generator::spawn_monster(ecs, monster, *x, *y, depth);
+    }
+    ecs.maintain();
+    existing + spawns.len()
+}
+
+// As if an alarm had gone off where the player stands, every turn
+fn alert_everyone(ecs: &mut World) {
+    let player = *ecs.fetch::<Point>();
+    let entities = ecs.entities();
+    let monsters = ecs.read_storage::<Monster>();
+    let mut memories = ecs.write_storage::<PlayerMemory>();
+    for (monster, _) in (&entities, &monsters).join() {
+        memories
+            .insert(
+                monster,
+                PlayerMemory {
+                    last_seen: Some(player),
+                    turns: MONSTER_MEMORY_TURNS,
+                },
+            )
+            .expect("Unable to insert memory");
+    }
+}
@@ -0,0 +1,44 @@
+use super::{Map, VIEW_X, VIEW_Y};
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+// The part of the map on screen: a VIEW_X by VIEW_Y window centred on the
+// player, pushed back inside the map near its edges so the view never
+// scrolls off into nothing
+#[derive(Clone, Copy)]
+pub struct Camera {
+    pub min_x: i32,
+    pub min_y: i32,
+}
+
+impl Camera {
+    pub fn new(map: &Map, center: Point) -> Camera {
+        Camera {
+            min_x: i32::clamp(center.x - VIEW_X / 2, 0, i32::max(0, map.width - VIEW_X)),
+            min_y: i32::clamp(center.y - VIEW_Y / 2, 0, i32::max(0, map.height - VIEW_Y)),
+        }
+    }
+
+    // The camera following the player around the current level
+    pub fn of_player(ecs: &World) -> Camera {
+        Camera::new(&ecs.fetch::<Map>(), *ecs.fetch::<Point>())
+    }
+
+    // Where a map position is drawn, if it is in view at all
+    pub fn to_screen(self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (screen_x, screen_y) = (x - self.min_x, y - self.min_y);
+        if screen_x < 0 || screen_x >= VIEW_X || screen_y < 0 || screen_y >= VIEW_Y {
+            return None;
+        }
+        Some((screen_x, screen_y))
+    }
+
+    // The map position under a screen cell, such as the mouse, if the cell is
+    // part of the view rather than the log below it
+    pub fn to_world(self, (screen_x, screen_y): (i32, i32)) -> Option<Point> {
+        if screen_x < 0 || screen_x >= VIEW_X || screen_y < 0 || screen_y >= VIEW_Y {
+            return None;
+        }
+        Some(Point::new(screen_x + self.min_x, screen_y + self.min_y))
+    }
+}
@@ -1,11 +1,61 @@
+use super::constants::{
+    BLUE_COLOR, BROWN_COLOR, CYAN_COLOR, GREEN_COLOR, ORANGE_COLOR, PURPLE_COLOR, RED_COLOR,
+    WHITE_COLOR, YELLOW_COLOR,
+};
+use super::map::Map;
+use super::RunState;
 use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
 use specs::prelude::*;
-use specs_derive::Component;
+use specs::saveload::{ConvertSaveload, Marker};
+use specs_derive::{Component, ConvertSaveload};
 
-#[derive(Component, Debug)]
+// A stack of arrows or the like, used up one at a time by a ranged weapon
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ammunition {
+    pub count: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct AreaOfEffect {
+    pub radius: i32,
+}
+
+// How a monster goes about fighting the player
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize, Default)]
+pub enum BehaviourKind {
+    // Runs straight at the player
+    #[default]
+    Chaser,
+    // Chases, but runs away once badly hurt
+    Coward,
+    // Keeps its distance and shoots
+    Kiter,
+    // Never leaves its post, fighting only what comes to it
+    Guard,
+    // Wanders the level until it spots the player, then chases
+    Patroller,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Behaviour {
+    pub kind: BehaviourKind,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct BlocksVisibility {}
+
+// Reveals the map within `radius` of whoever uses it, walls or not
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Clairvoyance {
+    pub radius: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct CombatStats {
     pub max_hp: i32,
     pub hp: i32,
@@ -17,50 +67,168 @@ pub struct CombatStats {
     pub magic: i32,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Consumable {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct DefenseBonus {
+    pub defense: i32,
+}
+
+// Locked doors also block the tile, until opened with a key
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Door {
+    pub open: bool,
+    pub locked: bool,
+}
+
+// Marks whatever moved this turn, so traps can tell who stepped on them
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntityMoved {}
+
+// Fires when something moves onto the same tile
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntryTrigger {}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Melee,
+    Shield,
+    Ranged,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct HealEffect {
     pub amount: i32,
 }
 
-#[derive(Component, Debug, Clone)]
+// Not drawn, nor known to the player, until spotted
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Hidden {}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum HungerState {
+    WellFed,
+    Normal,
+    Hungry,
+    Starving,
+}
+
+impl HungerState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HungerState::WellFed => "Well Fed",
+            HungerState::Normal => "Normal",
+            HungerState::Hungry => "Hungry",
+            HungerState::Starving => "Starving",
+        }
+    }
+
+    pub fn color(&self) -> (u8, u8, u8) {
+        match self {
+            HungerState::WellFed => GREEN_COLOR,
+            HungerState::Normal => WHITE_COLOR,
+            HungerState::Hungry => ORANGE_COLOR,
+            HungerState::Starving => RED_COLOR,
+        }
+    }
+}
+
+// Counts down the player's turns until the next, hungrier, state
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct HungerClock {
+    pub state: HungerState,
+    pub duration: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InBackpack {
     pub owner: Entity,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct InflictsDamage {
     pub damage: i32,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsStatus {
+    pub effect: StatusEffect,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
-#[derive(Component, Debug)]
+// Opens one locked door, and is used up doing so
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Key {}
+
+// Reveals the whole level
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MagicMapper {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MeleePowerBonus {
+    pub power: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Name {
     pub name: String,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Player {}
 
-#[derive(Component)]
+// A monster that knows the player is about. It hunts towards `last_seen`
+// for `turns` more turns, then forgets it and wanders.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct PlayerMemory {
+    pub last_seen: Option<Point>,
+    pub turns: i32,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesFood {}
+
+// Wakes every sleeping monster on the level
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RaisesAlarm {}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Ranged {
     pub range: i32,
 }
 
-#[derive(Component)]
+// Shoots up to `range` tiles away. Held by a wielded bow, or by a monster
+// that shoots on its own without needing ammunition.
+#[derive(Component, Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RangedWeapon {
+    pub range: i32,
+    pub power: i32,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Renderable {
     pub glyph: FontCharType,
     pub fg: RGB,
@@ -68,7 +236,100 @@ pub struct Renderable {
     pub render_order: i32,
 }
 
-#[derive(Component, Debug)]
+// Gone once it has fired
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct SingleActivation {}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum StatusKind {
+    Confusion,
+    Poison,
+    Sleep,
+    Haste,
+    Held,
+    Telepathy,
+}
+
+impl StatusKind {
+    // Short tag shown next to the health bar
+    pub fn code(&self) -> &'static str {
+        match self {
+            StatusKind::Confusion => "CNF",
+            StatusKind::Poison => "PSN",
+            StatusKind::Sleep => "SLP",
+            StatusKind::Haste => "HST",
+            StatusKind::Held => "HLD",
+            StatusKind::Telepathy => "TLP",
+        }
+    }
+
+    pub fn color(&self) -> (u8, u8, u8) {
+        match self {
+            StatusKind::Confusion => PURPLE_COLOR,
+            StatusKind::Poison => GREEN_COLOR,
+            StatusKind::Sleep => BLUE_COLOR,
+            StatusKind::Haste => YELLOW_COLOR,
+            StatusKind::Held => BROWN_COLOR,
+            StatusKind::Telepathy => CYAN_COLOR,
+        }
+    }
+
+    pub fn adjective(&self) -> &'static str {
+        match self {
+            StatusKind::Confusion => "confused",
+            StatusKind::Poison => "poisoned",
+            StatusKind::Sleep => "asleep",
+            StatusKind::Haste => "hasted",
+            StatusKind::Held => "held",
+            StatusKind::Telepathy => "telepathic",
+        }
+    }
+}
+
+// `potency` only matters for poison, as the damage dealt each turn
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns: i32,
+    pub potency: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+    // Haste alternates between an extra action and a normal turn
+    pub extra_action_taken: bool,
+}
+
+impl StatusEffects {
+    // Applying an effect that is already running refreshes it instead of
+    // stacking a second copy
+    pub fn apply(store: &mut WriteStorage<StatusEffects>, target: Entity, effect: StatusEffect) {
+        if let Some(statuses) = store.get_mut(target) {
+            match statuses.effects.iter_mut().find(|e| e.kind == effect.kind) {
+                Some(existing) => {
+                    existing.turns = i32::max(existing.turns, effect.turns);
+                    existing.potency = i32::max(existing.potency, effect.potency);
+                }
+                None => statuses.effects.push(effect),
+            }
+        } else {
+            let statuses = StatusEffects {
+                effects: vec![effect],
+                extra_action_taken: false,
+            };
+            store
+                .insert(target, statuses)
+                .expect("Unable to insert status effects");
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct SufferDamage {
     pub amount: Vec<i32>,
 }
@@ -86,34 +347,68 @@ impl SufferDamage {
     }
 }
 
-#[derive(Component)]
+// Sends whoever triggers it to a random spot on the level
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TeleportsTarget {}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Viewshed {
     pub visible_tiles: Vec<Point>,
     pub range: i32,
     pub dirty: bool,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct WantsToDisplayContent {}
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToDropItem {
     pub item: Entity,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToMelee {
     pub target: Entity,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToOpenDoor {
+    pub door: Entity,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToPickupItem {
     pub collected_by: Entity,
     pub item: Entity,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToRemoveItem {
+    pub item: Entity,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToShoot {
+    pub target: Point,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToUseItem {
     pub item: Entity,
     pub target: Option<Point>,
 }
+
+// Marker for entities that should be written to the save file
+pub struct SerializeMe;
+
+// Carries resources that are not components (map, log, seed) through the
+// save file. Only exists while saving or loading.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct SerializationHelper {
+    pub map: Map,
+    pub log: Vec<String>,
+    pub seed: u64,
+    // Where the random rolls carry on from, see save_game
+    pub rng_seed: u64,
+    pub runstate: RunState,
+}
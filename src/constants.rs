@@ -5,6 +5,8 @@ pub static WALL_COLOR: (u8, u8, u8) = (213, 196, 161);
 pub static WALL_COLOR_OOS: (u8, u8, u8) = (80, 73, 69);
 pub static FLOOR_COLOR: (u8, u8, u8) = (189, 174, 147);
 pub static FLOOR_COLOR_OOS: (u8, u8, u8) = (102, 92, 84);
+pub static STAIRS_COLOR: (u8, u8, u8) = (142, 192, 124);
+pub static STAIRS_COLOR_OOS: (u8, u8, u8) = (104, 157, 106);
 
 pub static RED_COLOR: (u8, u8, u8) = (251, 73, 52);
 pub static ORANGE_COLOR: (u8, u8, u8) = (254, 128, 25);
@@ -16,13 +18,59 @@ pub static PURPLE_COLOR: (u8, u8, u8) = (211, 134, 155);
 pub static BROWN_COLOR: (u8, u8, u8) = (214, 93, 14);
 pub static WHITE_COLOR: (u8, u8, u8) = (251, 241, 199);
 
-pub static MAP_X: i32 = 80;
-pub static MAP_Y: i32 = 43;
+// The map view takes the top of the 80x50 console, above the log. Levels
+// can be any size; the camera scrolls over the ones larger than the view.
+pub static VIEW_X: i32 = 80;
+pub static VIEW_Y: i32 = 43;
+pub static MAP_X: i32 = 100;
+pub static MAP_Y: i32 = 60;
+pub static DEPTH_OFFSET: i32 = 2;
+pub static STATUS_OFFSET: i32 = 13;
 pub static HEALTH_OFFSET: i32 = 28;
 pub static HEALTHBAR_OFFSET: i32 = HEALTH_OFFSET + 12;
 pub static EXP_OFFSET: i32 = HEALTHBAR_OFFSET + 14;
 pub static EXPBAR_OFFSET: i32 = EXP_OFFSET + 14;
 pub static LOG_OFFSET: i32 = 2;
 
-pub static MAX_MONSTERS_PER_ROOM: i32 = 4;
-pub static MAX_ITEMS_PER_ROOM: i32 = 2;
+// Player turns spent in each hunger state before the next one sets in
+pub static WELL_FED_TURNS: i32 = 20;
+pub static NORMAL_TURNS: i32 = 200;
+pub static HUNGRY_TURNS: i32 = 200;
+pub static STARVATION_DAMAGE: i32 = 1;
+pub static HUNGER_OFFSET: i32 = 66;
+
+pub static MAPGEN_FRAMES_PER_SNAPSHOT: usize = 4;
+
+pub static MAX_MONSTERS_PER_REGION: i32 = 4;
+pub static MAX_ITEMS_PER_REGION: i32 = 2;
+pub static MAX_TRAPS_PER_REGION: i32 = 2;
+// About as many rooms as a level of rooms ends up with. Open levels are cut
+// into more regions than that, and only this many of them are filled.
+pub static MAX_SPAWN_REGIONS: i32 = 20;
+// A d20 plus the player's agility has to beat this to spot a hidden trap,
+// with a bonus when searching for them on purpose
+pub static TRAP_CONCEALMENT: i32 = 21;
+pub static SEARCH_BONUS: i32 = 8;
+pub static SEARCH_RADIUS: f32 = 3.0;
+// One door in this many is locked
+pub static LOCKED_DOOR_CHANCE: i32 = 10;
+
+// Chance out of 100 that a shot hits, losing some for every tile past the
+// first but never dropping below the minimum
+pub static RANGED_ACCURACY: i32 = 95;
+pub static RANGED_ACCURACY_FALLOFF: i32 = 8;
+pub static RANGED_MIN_ACCURACY: i32 = 25;
+
+// Cowards run once their health drops to this percentage of the maximum
+pub static COWARD_FLEE_PERCENT: i32 = 35;
+// Kiters back away from a player any closer than this
+pub static KITE_DISTANCE: f32 = 3.0;
+// How many turns a monster keeps hunting the player after losing sight of
+// them, and how far away other monsters hear it raise the alarm
+pub static MONSTER_MEMORY_TURNS: i32 = 15;
+pub static ALERT_RADIUS: f32 = 8.0;
+// Scales the distances to the player into the map monsters flee along. Past
+// -1 it makes open ground further off worth more than a dead end close by.
+pub static FLEE_MAP_WEIGHT: f32 = -1.2;
+// Health regained by waiting a turn with no monster in sight and no hunger
+pub static REST_HEAL_AMOUNT: i32 = 1;
@@ -0,0 +1,98 @@
+use super::{
+    BlocksTile, BlocksVisibility, Door, GameLog, InBackpack, Key, Name, Renderable, Viewshed,
+    WantsToOpenDoor,
+};
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+pub struct DoorSystem {}
+
+impl<'a> System<'a> for DoorSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToOpenDoor>,
+        WriteStorage<'a, Door>,
+        WriteStorage<'a, BlocksTile>,
+        WriteStorage<'a, BlocksVisibility>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Key>,
+        ReadStorage<'a, Name>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_open,
+            mut doors,
+            mut blocks_tile,
+            mut blocks_visibility,
+            mut renderables,
+            mut viewsheds,
+            backpack,
+            keys,
+            names,
+        ) = data;
+
+        let mut opened_any = false;
+        for (entity, wants_open) in (&entities, &wants_open).join() {
+            let door = match doors.get_mut(wants_open.door) {
+                Some(door) if !door.open => door,
+                _ => continue,
+            };
+
+            if door.locked {
+                let key = (&entities, &backpack, &keys)
+                    .join()
+                    .find(|(_, pack, _)| pack.owner == entity)
+                    .map(|(key, _, _)| key);
+                let key = match key {
+                    Some(key) => key,
+                    None => {
+                        if entity == *player_entity {
+                            gamelog.entries.push("The door is locked.".to_string());
+                        }
+                        continue;
+                    }
+                };
+
+                if entity == *player_entity {
+                    let key_name = if let Some(name) = names.get(key) {
+                        name.name.as_str()
+                    } else {
+                        "key"
+                    };
+                    gamelog
+                        .entries
+                        .push(format!("You unlock the door with the {}.", key_name));
+                }
+                entities.delete(key).expect("Delete failed");
+                door.locked = false;
+                blocks_tile.remove(wants_open.door);
+            } else if entity == *player_entity {
+                gamelog.entries.push("You open the door.".to_string());
+            }
+
+            door.open = true;
+            blocks_visibility.remove(wants_open.door);
+            if let Some(renderable) = renderables.get_mut(wants_open.door) {
+                renderable.glyph = to_cp437('\'');
+            }
+            opened_any = true;
+        }
+
+        wants_open.clear();
+
+        // An open door changes what everyone around it can see
+        if opened_any {
+            for viewshed in (&mut viewsheds).join() {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
@@ -1,10 +1,17 @@
+use super::raws::{ItemDef, MonsterDef, RawMaster, TrapDef};
+use super::SerializeMe;
 use super::{
-    BlocksTile, CombatStats, Consumable, HealEffect, InflictsDamage, Item, Monster, Name, Player,
-    Position, Ranged, Rect, Renderable, Viewshed, BASE_BG_COLOR, BROWN_SHIRT_COLOR, CYAN_COLOR,
-    MAX_ITEMS_PER_ROOM, MAX_MONSTERS_PER_ROOM, PLAYER_COLOR, PURPLE_COLOR,
+    Ammunition, AreaOfEffect, Behaviour, BlocksTile, BlocksVisibility, Clairvoyance, CombatStats,
+    Consumable, DefenseBonus, Door, EntryTrigger, Equippable, HealEffect, Hidden, HungerClock,
+    HungerState, InflictsDamage, InflictsStatus, Item, Key, MagicMapper, Map, MeleePowerBonus,
+    Monster, Name, Player, Position, ProvidesFood, RaisesAlarm, Ranged, RangedWeapon, Renderable,
+    SingleActivation, StatusEffect, TeleportsTarget, Viewshed, BASE_BG_COLOR, BROWN_COLOR,
+    LOCKED_DOOR_CHANCE, MAX_ITEMS_PER_REGION, MAX_MONSTERS_PER_REGION, MAX_SPAWN_REGIONS,
+    MAX_TRAPS_PER_REGION, PLAYER_COLOR, WELL_FED_TURNS,
 };
 use bracket_lib::prelude::*;
 use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
 
 // Spawns player in the specified location and returns entity
 pub fn spawn_player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
@@ -38,148 +45,348 @@ pub fn spawn_player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             vitality: 6,
             magic: 2,
         })
+        .with(HungerClock {
+            state: HungerState::WellFed,
+            duration: WELL_FED_TURNS,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
 
-pub fn spawn_room_contents(ecs: &mut World, room: &Rect) {
-    let mut monster_spawn_points: Vec<(usize, usize)> = Vec::new();
-    let mut item_spawn_points: Vec<(usize, usize)> = Vec::new();
+// Fills the level's regions, leaving some out at random when there are more
+// than a level of rooms would have, so open levels are no more crowded
+pub fn spawn_regions(ecs: &mut World, regions: &[Vec<(i32, i32)>], depth: i32) {
+    let mut regions = regions.to_vec();
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        while regions.len() > MAX_SPAWN_REGIONS as usize {
+            regions.remove(rng.range(0, regions.len() as i32) as usize);
+        }
+    }
+
+    for region in regions.iter() {
+        spawn_region(ecs, region, depth);
+    }
+}
+
+// Deeper levels hold more, and tougher, monsters and stronger items. Each
+// one lands on its own tile of the region, and traps lie hidden among them.
+fn spawn_region(ecs: &mut World, region: &[(i32, i32)], depth: i32) {
+    let mut monster_spawns: Vec<(i32, i32, MonsterDef)> = Vec::new();
+    let mut item_spawns: Vec<(i32, i32, ItemDef)> = Vec::new();
+    let mut trap_spawns: Vec<(i32, i32, TrapDef)> = Vec::new();
 
     {
+        let raws = ecs.fetch::<RawMaster>();
+        let monster_table = raws.monster_table();
+        let item_table = raws.item_table();
+        let trap_table = raws.trap_table();
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_monsters = rng.roll_dice(1, MAX_MONSTERS_PER_ROOM + 2) - 3;
-        let num_items = rng.roll_dice(1, MAX_ITEMS_PER_ROOM + 2) - 3;
+        let num_monsters = rng.roll_dice(1, MAX_MONSTERS_PER_REGION + depth + 1) - 3;
+        let num_items = rng.roll_dice(1, MAX_ITEMS_PER_REGION + 2) - 3;
+        let num_traps = rng.roll_dice(1, MAX_TRAPS_PER_REGION + 2) - 3;
 
+        // Monsters, items and traps all draw from the same tiles, so no two
+        // of them end up on one
+        let mut free_tiles = region.to_vec();
         for _i in 0..num_monsters {
-            loop {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                if !monster_spawn_points.contains(&(x, y)) {
-                    monster_spawn_points.push((x, y));
-                    break;
-                }
+            if free_tiles.is_empty() {
+                break;
+            }
+            let (x, y) = free_tiles.remove(rng.range(0, free_tiles.len() as i32) as usize);
+            let monster = monster_table
+                .roll(&mut rng, depth)
+                .and_then(|name| raws.monster(name));
+            if let Some(monster) = monster {
+                monster_spawns.push((x, y, monster.clone()));
             }
         }
 
         for _i in 0..num_items {
-            loop {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                if !item_spawn_points.contains(&(x, y)) {
-                    item_spawn_points.push((x, y));
-                    break;
-                }
+            if free_tiles.is_empty() {
+                break;
+            }
+            let (x, y) = free_tiles.remove(rng.range(0, free_tiles.len() as i32) as usize);
+            let item = item_table
+                .roll(&mut rng, depth)
+                .and_then(|name| raws.item(name));
+            if let Some(item) = item {
+                item_spawns.push((x, y, item.clone()));
             }
         }
+
+        for _i in 0..num_traps {
+            if free_tiles.is_empty() {
+                break;
+            }
+            let (x, y) = free_tiles.remove(rng.range(0, free_tiles.len() as i32) as usize);
+            let trap = trap_table
+                .roll(&mut rng, depth)
+                .and_then(|name| raws.trap(name));
+            if let Some(trap) = trap {
+                trap_spawns.push((x, y, trap.clone()));
+            }
+        }
+    }
+
+    for (x, y, monster) in monster_spawns.iter() {
+        spawn_monster(ecs, monster, *x, *y, depth);
     }
 
-    for (x, y) in monster_spawn_points.iter() {
-        random_monster(ecs, *x as i32, *y as i32);
+    for (x, y, item) in item_spawns.iter() {
+        spawn_item(ecs, item, *x, *y, depth);
     }
 
-    for (x, y) in item_spawn_points.iter() {
-        random_item(ecs, *x as i32, *y as i32);
+    for (x, y, trap) in trap_spawns.iter() {
+        spawn_trap(ecs, trap, *x, *y, depth);
     }
 }
 
-fn random_monster(ecs: &mut World, x: i32, y: i32) {
-    let result;
+// Some doors are locked, and each locked door gets a key somewhere the player
+// can reach without going through a locked door, so the way down is never
+// shut off for good
+pub fn spawn_doors(
+    ecs: &mut World,
+    map: &Map,
+    start: (i32, i32),
+    doors: &[(i32, i32)],
+    depth: i32,
+) {
+    // Without a key to find, no door can be locked
+    let key = ecs
+        .fetch::<RawMaster>()
+        .items
+        .iter()
+        .find(|item| item.key)
+        .cloned();
+
+    let mut locked_doors: Vec<(i32, i32)> = Vec::new();
+    let mut key_spawns: Vec<(i32, i32)> = Vec::new();
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        result = rng.roll_dice(1, 2);
+        if key.is_some() {
+            for door in doors.iter() {
+                if rng.roll_dice(1, LOCKED_DOOR_CHANCE) == 1 {
+                    locked_doors.push(*door);
+                }
+            }
+        }
+
+        let mut free_tiles = map.reachable_tiles(start, &locked_doors);
+        free_tiles.retain(|tile| *tile != start && !doors.contains(tile));
+        for _ in locked_doors.iter() {
+            if free_tiles.is_empty() {
+                break;
+            }
+            key_spawns.push(free_tiles.remove(rng.range(0, free_tiles.len() as i32) as usize));
+        }
+    }
+
+    for &(x, y) in doors.iter() {
+        spawn_door(ecs, x, y, locked_doors.contains(&(x, y)));
     }
 
-    match result {
-        1 => skeleton(ecs, x, y),
-        _ => goblin(ecs, x, y),
-    };
+    if let Some(key) = key {
+        for (x, y) in key_spawns {
+            spawn_item(ecs, &key, x, y, depth);
+        }
+    }
 }
 
-fn random_item(ecs: &mut World, x: i32, y: i32) {
-    let result;
-    {
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        result = rng.roll_dice(1, 2);
+fn spawn_door(ecs: &mut World, x: i32, y: i32, locked: bool) {
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('+'),
+            fg: RGB::named(BROWN_COLOR),
+            bg: RGB::named(BASE_BG_COLOR),
+            render_order: 3,
+        })
+        .with(Name {
+            name: "Door".to_string(),
+        })
+        .with(Door {
+            open: false,
+            locked,
+        })
+        .with(BlocksVisibility {});
+
+    if locked {
+        builder = builder.with(BlocksTile {});
     }
 
-    match result {
-        1 => health_potion(ecs, x, y),
-        _ => magic_missile_scroll(ecs, x, y),
-    };
+    builder.marked::<SimpleMarker<SerializeMe>>().build();
 }
 
-fn skeleton(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, to_cp437('s'), "Skeleton");
-}
-fn goblin(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, to_cp437('g'), "Goblin");
+fn glyph(definition: &str) -> FontCharType {
+    to_cp437(definition.chars().next().unwrap_or('?'))
 }
 
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: FontCharType, name: S) {
-    ecs.create_entity()
+pub fn spawn_monster(ecs: &mut World, monster: &MonsterDef, x: i32, y: i32, depth: i32) {
+    let stats = &monster.stats;
+    let vitality = stats.vitality.at(depth);
+    let mut builder = ecs
+        .create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            glyph,
-            fg: RGB::named(BROWN_SHIRT_COLOR),
+            glyph: glyph(&monster.glyph),
+            fg: RGB::named(monster.color),
             bg: RGB::named(BASE_BG_COLOR),
             render_order: 1,
         })
         .with(Viewshed {
             visible_tiles: Vec::new(),
-            range: 6,
+            range: monster.vision,
             dirty: true,
         })
         .with(Monster {})
+        .with(Behaviour {
+            kind: monster.behaviour,
+        })
         .with(Name {
-            name: name.to_string(),
+            name: monster.name.clone(),
         })
         .with(CombatStats {
-            max_hp: 5 * 3,
-            hp: 5 * 3,
-            level: 1,
+            max_hp: 5 * vitality,
+            hp: 5 * vitality,
+            level: stats.level.at(depth),
             exp: 0,
-            strength: 4,
-            agility: 1,
-            vitality: 3,
-            magic: 0,
+            strength: stats.strength.at(depth),
+            agility: stats.agility.at(depth),
+            vitality,
+            magic: stats.magic.at(depth),
         })
-        .with(BlocksTile {})
-        .build();
+        .with(BlocksTile {});
+
+    if let Some(weapon) = monster.ranged {
+        builder = builder.with(RangedWeapon {
+            range: weapon.range,
+            power: weapon.power,
+        });
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build();
 }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
+fn spawn_item(ecs: &mut World, item: &ItemDef, x: i32, y: i32, depth: i32) {
+    let mut builder = ecs
+        .create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            glyph: to_cp437('i'),
-            fg: RGB::named(PURPLE_COLOR),
+            glyph: glyph(&item.glyph),
+            fg: RGB::named(item.color),
             bg: RGB::named(BASE_BG_COLOR),
             render_order: 2,
         })
         .with(Name {
-            name: "Health Potion".to_string(),
+            name: item.name.clone(),
         })
-        .with(Item {})
-        .with(Consumable {})
-        .with(HealEffect { amount: 8 })
-        .build();
+        .with(Item {});
+
+    if item.consumable {
+        builder = builder.with(Consumable {});
+    }
+    if let Some(heal) = item.heal {
+        builder = builder.with(HealEffect {
+            amount: heal.at(depth),
+        });
+    }
+    if let Some(damage) = item.damage {
+        builder = builder.with(InflictsDamage {
+            damage: damage.at(depth),
+        });
+    }
+    if let Some(range) = item.range {
+        builder = builder.with(Ranged { range });
+    }
+    if let Some(radius) = item.area_of_effect {
+        builder = builder.with(AreaOfEffect { radius });
+    }
+    if let Some(status) = &item.status {
+        builder = builder.with(InflictsStatus {
+            effect: StatusEffect {
+                kind: status.kind,
+                turns: status.turns,
+                potency: status.potency.at(depth),
+            },
+        });
+    }
+    if item.food {
+        builder = builder.with(ProvidesFood {});
+    }
+    if item.key {
+        builder = builder.with(Key {});
+    }
+    if item.magic_mapping {
+        builder = builder.with(MagicMapper {});
+    }
+    if let Some(radius) = item.clairvoyance {
+        builder = builder.with(Clairvoyance { radius });
+    }
+    if let Some(weapon) = item.ranged_weapon {
+        builder = builder.with(RangedWeapon {
+            range: weapon.range,
+            power: weapon.power,
+        });
+    }
+    if let Some(count) = item.ammo {
+        builder = builder.with(Ammunition { count });
+    }
+    if let Some(equip) = &item.equippable {
+        builder = builder.with(Equippable { slot: equip.slot });
+        if equip.power != 0 {
+            builder = builder.with(MeleePowerBonus { power: equip.power });
+        }
+        if equip.defense != 0 {
+            builder = builder.with(DefenseBonus {
+                defense: equip.defense,
+            });
+        }
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build();
 }
 
-fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
+fn spawn_trap(ecs: &mut World, trap: &TrapDef, x: i32, y: i32, depth: i32) {
+    let mut builder = ecs
+        .create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            glyph: to_cp437(')'),
-            fg: RGB::named(CYAN_COLOR),
+            glyph: glyph(&trap.glyph),
+            fg: RGB::named(trap.color),
             bg: RGB::named(BASE_BG_COLOR),
-            render_order: 2,
+            render_order: 3,
         })
         .with(Name {
-            name: "Magic Missile Scroll".to_string(),
+            name: trap.name.clone(),
         })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
-        .build();
+        .with(Hidden {})
+        .with(EntryTrigger {});
+
+    if let Some(damage) = trap.damage {
+        builder = builder.with(InflictsDamage {
+            damage: damage.at(depth),
+        });
+    }
+    if let Some(status) = &trap.status {
+        builder = builder.with(InflictsStatus {
+            effect: StatusEffect {
+                kind: status.kind,
+                turns: status.turns,
+                potency: status.potency.at(depth),
+            },
+        });
+    }
+    if trap.teleport {
+        builder = builder.with(TeleportsTarget {});
+    }
+    if trap.alarm {
+        builder = builder.with(RaisesAlarm {});
+    }
+    if trap.single_use {
+        builder = builder.with(SingleActivation {});
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build();
 }
@@ -0,0 +1,175 @@
+use super::input::{key_from_name, Input};
+use super::map_builders::BuilderKind;
+use super::raws::RawMaster;
+use super::{CombatStats, GameLog, InBackpack, Map, Monster, Player, Position, RunState, State};
+use serde::Serialize;
+use specs::prelude::*;
+use std::fs;
+
+// Guards against scripts that leave the game spinning without ever asking
+// for input again
+const MAX_FRAMES_PER_GAME: u64 = 1_000_000;
+const LOG_LINES_REPORTED: usize = 5;
+
+#[derive(Serialize)]
+pub struct Report {
+    pub seed: u64,
+    pub outcome: &'static str,
+    pub turns: u64,
+    pub depth: i32,
+    pub hp: i32,
+    pub max_hp: i32,
+    pub level: i32,
+    pub exp: i32,
+    pub position: (i32, i32),
+    pub items_carried: usize,
+    pub monsters_remaining: usize,
+    pub log: Vec<String>,
+}
+
+// Scripts hold one input per line:
+//   key <VirtualKeyCode name>   e.g. "key L", "key Numpad6", "key Escape"
+//   click <x> <y>               left click on a screen cell
+// Blank lines and lines starting with '#' are ignored.
+pub fn parse_script(text: &str) -> Result<Vec<Input>, String> {
+    let mut inputs = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let input = match words.as_slice() {
+            ["key", name] => key_from_name(name).map(Input::key),
+            ["click", x, y] => match (x.parse(), y.parse()) {
+                (Ok(x), Ok(y)) => Some(Input::click(x, y)),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match input {
+            Some(input) => inputs.push(input),
+            None => return Err(format!("line {}: cannot parse '{}'", number + 1, line)),
+        }
+    }
+
+    Ok(inputs)
+}
+
+// Plays one game per seed, starting from the given one, and prints a JSON
+// report line for each
+pub fn run(
+    script_path: &str,
+    seed: u64,
+    games: u64,
+    raws: &RawMaster,
+    map_builder: Option<BuilderKind>,
+) -> Result<(), String> {
+    let text = fs::read_to_string(script_path)
+        .map_err(|e| format!("unable to read script '{}': {}", script_path, e))?;
+    let script = parse_script(&text)?;
+
+    for game in 0..games {
+        let report = play(seed.wrapping_add(game), &script, raws, map_builder);
+        println!(
+            "{}",
+            serde_json::to_string(&report).map_err(|e| e.to_string())?
+        );
+    }
+
+    Ok(())
+}
+
+// Steps the game exactly like the windowed loop does, feeding the next
+// scripted input each time the game waits on the player
+pub fn play(
+    seed: u64,
+    script: &[Input],
+    raws: &RawMaster,
+    map_builder: Option<BuilderKind>,
+) -> Report {
+    let mut gs = State::new(seed, raws.clone(), map_builder);
+    gs.saves_enabled = false;
+
+    let mut inputs = script.iter();
+    let mut turns = 0;
+    let mut outcome = "frame_limit";
+
+    for _ in 0..MAX_FRAMES_PER_GAME {
+        let runstate = *gs.ecs.fetch::<RunState>();
+        if runstate == RunState::Dead {
+            outcome = "dead";
+            break;
+        }
+        if runstate == RunState::PlayerTurn {
+            turns += 1;
+        }
+
+        let input = if runstate.awaits_input() {
+            match inputs.next() {
+                Some(input) => *input,
+                None => {
+                    outcome = "script_finished";
+                    break;
+                }
+            }
+        } else {
+            Input::default()
+        };
+
+        if gs.update(&input) {
+            outcome = "quit";
+            break;
+        }
+    }
+
+    report(&gs.ecs, seed, outcome, turns)
+}
+
+fn report(ecs: &World, seed: u64, outcome: &'static str, turns: u64) -> Report {
+    let player_entity = *ecs.fetch::<Entity>();
+    let stats = ecs.read_storage::<CombatStats>();
+    let positions = ecs.read_storage::<Position>();
+    let players = ecs.read_storage::<Player>();
+    let monsters = ecs.read_storage::<Monster>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let map = ecs.fetch::<Map>();
+    let log = ecs.fetch::<GameLog>();
+
+    let (hp, max_hp, level, exp) = match stats.get(player_entity) {
+        Some(stats) => (stats.hp, stats.max_hp, stats.level, stats.exp),
+        None => (0, 0, 0, 0),
+    };
+    let position = match positions.get(player_entity) {
+        Some(pos) => (pos.x, pos.y),
+        None => (0, 0),
+    };
+
+    Report {
+        seed,
+        outcome,
+        turns,
+        depth: map.depth,
+        hp,
+        max_hp,
+        level,
+        exp,
+        position,
+        items_carried: backpack
+            .join()
+            .filter(|pack| pack.owner == player_entity)
+            .count(),
+        monsters_remaining: (&monsters, &stats, !&players).join().count(),
+        log: log
+            .entries
+            .iter()
+            .rev()
+            .take(LOG_LINES_REPORTED)
+            .rev()
+            .cloned()
+            .collect(),
+    }
+}
@@ -0,0 +1,73 @@
+use super::{
+    GameLog, HungerClock, HungerState, RunState, SufferDamage, HUNGRY_TURNS, NORMAL_TURNS,
+    STARVATION_DAMAGE,
+};
+use specs::prelude::*;
+
+pub struct HungerSystem {}
+
+impl<'a> System<'a> for HungerSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        Entities<'a>,
+        WriteStorage<'a, HungerClock>,
+        WriteStorage<'a, SufferDamage>,
+        WriteExpect<'a, GameLog>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, runstate, entities, mut hunger_clocks, mut suffer_damage, mut gamelog) =
+            data;
+
+        // Only the player's own actions make them hungrier
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        for (entity, clock) in (&entities, &mut hunger_clocks).join() {
+            let is_player = entity == *player_entity;
+
+            clock.duration -= 1;
+            if clock.duration > 0 {
+                continue;
+            }
+
+            match clock.state {
+                HungerState::WellFed => {
+                    clock.state = HungerState::Normal;
+                    clock.duration = NORMAL_TURNS;
+                    if is_player {
+                        gamelog
+                            .entries
+                            .push("You are no longer well fed.".to_string());
+                    }
+                }
+                HungerState::Normal => {
+                    clock.state = HungerState::Hungry;
+                    clock.duration = HUNGRY_TURNS;
+                    if is_player {
+                        gamelog.entries.push("You are hungry.".to_string());
+                    }
+                }
+                HungerState::Hungry => {
+                    clock.state = HungerState::Starving;
+                    clock.duration = 0;
+                    if is_player {
+                        gamelog.entries.push("You are starving!".to_string());
+                    }
+                }
+                // Starving hurts every turn until something is eaten
+                HungerState::Starving => {
+                    SufferDamage::new_damage(&mut suffer_damage, entity, STARVATION_DAMAGE);
+                    if is_player {
+                        gamelog.entries.push(format!(
+                            "You suffer {} damage from hunger.",
+                            STARVATION_DAMAGE
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use bracket_lib::prelude::*;
+
+// Everything the game reads from the player during one frame. Game logic
+// only ever sees this, never the terminal, so it can be fed from a script.
+#[derive(PartialEq, Copy, Clone, Default)]
+pub struct Input {
+    pub key: Option<VirtualKeyCode>,
+    pub mouse_pos: (i32, i32),
+    pub left_click: bool,
+}
+
+impl Input {
+    pub fn from_context(ctx: &BTerm) -> Input {
+        Input {
+            key: ctx.key,
+            mouse_pos: ctx.mouse_pos(),
+            left_click: ctx.left_click,
+        }
+    }
+
+    pub fn key(key: VirtualKeyCode) -> Input {
+        Input {
+            key: Some(key),
+            ..Input::default()
+        }
+    }
+
+    pub fn click(x: i32, y: i32) -> Input {
+        Input {
+            key: None,
+            mouse_pos: (x, y),
+            left_click: true,
+        }
+    }
+}
+
+// Keys that can be named in scripts, by their VirtualKeyCode variant name
+const NAMED_KEYS: &[VirtualKeyCode] = &[
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Numpad0,
+    VirtualKeyCode::Numpad1,
+    VirtualKeyCode::Numpad2,
+    VirtualKeyCode::Numpad3,
+    VirtualKeyCode::Numpad4,
+    VirtualKeyCode::Numpad5,
+    VirtualKeyCode::Numpad6,
+    VirtualKeyCode::Numpad7,
+    VirtualKeyCode::Numpad8,
+    VirtualKeyCode::Numpad9,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Escape,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Period,
+    VirtualKeyCode::Comma,
+    VirtualKeyCode::Slash,
+    VirtualKeyCode::Tab,
+];
+
+pub fn key_name(key: VirtualKeyCode) -> String {
+    format!("{:?}", key)
+}
+
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    NAMED_KEYS
+        .iter()
+        .find(|key| key_name(**key) == name)
+        .copied()
+}
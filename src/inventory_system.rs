@@ -1,7 +1,10 @@
 use super::{
-    gamelog::GameLog, CombatStats, Consumable, HealEffect, InBackpack, InflictsDamage, Map, Name,
-    Position, SufferDamage, WantsToDropItem, WantsToPickupItem, WantsToUseItem,
+    gamelog::GameLog, Ammunition, AreaOfEffect, Clairvoyance, CombatStats, Consumable, Equippable,
+    Equipped, HealEffect, HungerClock, HungerState, InBackpack, InflictsDamage, InflictsStatus,
+    MagicMapper, Map, Name, Position, ProvidesFood, RunState, StatusEffects, SufferDamage,
+    WantsToDropItem, WantsToPickupItem, WantsToRemoveItem, WantsToUseItem, WELL_FED_TURNS,
 };
+use bracket_lib::prelude::*;
 use specs::prelude::*;
 
 pub struct ItemCollectionSystem {}
@@ -14,22 +17,54 @@ impl<'a> System<'a> for ItemCollectionSystem {
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        Entities<'a>,
+        WriteStorage<'a, Ammunition>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            player_entity,
+            mut gamelog,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            entities,
+            mut ammunition,
+        ) = data;
 
         for pickup in wants_pickup.join() {
             positions.remove(pickup.item);
-            backpack
-                .insert(
-                    pickup.item,
-                    InBackpack {
-                        owner: pickup.collected_by,
-                    },
-                )
-                .expect("Unable to add to backpack");
+
+            // Ammunition joins a stack of the same kind already carried
+            let picked_count = ammunition.get(pickup.item).map(|ammo| ammo.count);
+            let picked_name = names.get(pickup.item).map(|name| &name.name);
+            let stack = (&entities, &backpack, &names)
+                .join()
+                .find(|(item, pack, name)| {
+                    pack.owner == pickup.collected_by
+                        && ammunition.contains(*item)
+                        && Some(&name.name) == picked_name
+                })
+                .map(|(item, _pack, _name)| item);
+            match (picked_count, stack) {
+                (Some(count), Some(stack)) => {
+                    if let Some(ammo) = ammunition.get_mut(stack) {
+                        ammo.count += count;
+                    }
+                    entities.delete(pickup.item).expect("Delete failed");
+                }
+                _ => {
+                    backpack
+                        .insert(
+                            pickup.item,
+                            InBackpack {
+                                owner: pickup.collected_by,
+                            },
+                        )
+                        .expect("Unable to add to backpack");
+                }
+            }
 
             if pickup.collected_by == *player_entity {
                 gamelog.entries.push(format!(
@@ -49,7 +84,7 @@ impl<'a> System<'a> for ItemUseSystem {
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
-        ReadExpect<'a, Map>,
+        WriteExpect<'a, Map>,
         Entities<'a>,
         WriteStorage<'a, WantsToUseItem>,
         ReadStorage<'a, Name>,
@@ -58,13 +93,25 @@ impl<'a> System<'a> for ItemUseSystem {
         WriteStorage<'a, CombatStats>,
         ReadStorage<'a, Consumable>,
         WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Equippable>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, AreaOfEffect>,
+        ReadStorage<'a, InflictsStatus>,
+        WriteStorage<'a, StatusEffects>,
+        ReadStorage<'a, ProvidesFood>,
+        WriteStorage<'a, HungerClock>,
+        ReadStorage<'a, MagicMapper>,
+        ReadStorage<'a, Clairvoyance>,
+        ReadStorage<'a, Position>,
+        WriteExpect<'a, RunState>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             player_entity,
             mut gamelog,
-            map,
+            mut map,
             entities,
             mut wants_use_item,
             names,
@@ -73,9 +120,21 @@ impl<'a> System<'a> for ItemUseSystem {
             mut combat_stats,
             consumables,
             mut suffer_damage,
+            equippable,
+            mut equipped,
+            mut backpack,
+            area_of_effect,
+            inflicts_status,
+            mut statuses,
+            provides_food,
+            mut hunger_clocks,
+            magic_mappers,
+            clairvoyance,
+            positions,
+            mut runstate,
         ) = data;
 
-        for (entity, useitem, stats) in (&entities, &wants_use_item, &mut combat_stats).join() {
+        for (entity, useitem) in (&entities, &wants_use_item).join() {
             let consumable = consumables.get(useitem.item);
             match consumable {
                 None => {}
@@ -88,7 +147,9 @@ impl<'a> System<'a> for ItemUseSystem {
             match heal_effect {
                 None => {}
                 Some(eff) => {
-                    stats.hp = i32::min(stats.max_hp, stats.hp + eff.amount);
+                    if let Some(stats) = combat_stats.get_mut(entity) {
+                        stats.hp = i32::min(stats.max_hp, stats.hp + eff.amount);
+                    }
                     if entity == *player_entity {
                         gamelog.entries.push(format!(
                             "You use the {}, healing {} hp.",
@@ -99,27 +160,166 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
 
+            if provides_food.contains(useitem.item) {
+                if let Some(clock) = hunger_clocks.get_mut(entity) {
+                    clock.state = HungerState::WellFed;
+                    clock.duration = WELL_FED_TURNS;
+                }
+                if entity == *player_entity {
+                    gamelog.entries.push(format!(
+                        "You eat the {}.",
+                        names.get(useitem.item).unwrap().name
+                    ));
+                }
+            }
+
+            // Only the player has a map to fill in. The whole level is swept
+            // into view over the next few frames.
+            if magic_mappers.contains(useitem.item) && entity == *player_entity {
+                gamelog
+                    .entries
+                    .push("The layout of the level floods your mind!".to_string());
+                *runstate = RunState::MagicMapReveal { row: 0 };
+            }
+
+            let sight = clairvoyance.get(useitem.item);
+            if let (Some(sight), Some(pos)) = (sight, positions.get(entity)) {
+                if entity == *player_entity {
+                    let center = Point::new(pos.x, pos.y);
+                    for x in pos.x - sight.radius..=pos.x + sight.radius {
+                        for y in pos.y - sight.radius..=pos.y + sight.radius {
+                            let in_bounds = x >= 0 && x < map.width && y >= 0 && y < map.height;
+                            let distance =
+                                DistanceAlg::Pythagoras.distance2d(center, Point::new(x, y));
+                            if in_bounds && distance <= sight.radius as f32 {
+                                map.revealed_tiles[x as usize][y as usize] = true;
+                            }
+                        }
+                    }
+                    gamelog
+                        .entries
+                        .push("Your mind's eye reaches through the walls.".to_string());
+                }
+            }
+
+            // Everything that can take damage on the target tile, or within
+            // the blast around it
+            let mut targets: Vec<Entity> = Vec::new();
+            if let Some(target_point) = useitem.target {
+                let tiles = match area_of_effect.get(useitem.item) {
+                    None => vec![target_point],
+                    Some(area) => map.blast_area(target_point, area.radius),
+                };
+                for tile in tiles.iter() {
+                    for mob in map.tile_content[tile.x as usize][tile.y as usize].iter() {
+                        if combat_stats.contains(*mob) {
+                            targets.push(*mob);
+                        }
+                    }
+                }
+            }
+
             let damage_effect = inflict_damage.get(useitem.item);
             match damage_effect {
                 None => {}
                 Some(damage) => {
-                    // TODO use pattern matching here
-                    let target_point = useitem.target.unwrap();
-                    for mob in
-                        map.tile_content[target_point.x as usize][target_point.y as usize].iter()
-                    {
+                    for mob in targets.iter() {
                         SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage);
                         if entity == *player_entity {
                             let mob_name = names.get(*mob).unwrap();
                             let item_name = names.get(useitem.item).unwrap();
-                            gamelog.entries.push(format!(
-                                "You use {} on {}, inflicting {} damage",
-                                item_name.name, mob_name.name, damage.damage
-                            ));
+                            if *mob == entity {
+                                gamelog.entries.push(format!(
+                                    "You are caught in the blast of {}, taking {} damage",
+                                    item_name.name, damage.damage
+                                ));
+                            } else {
+                                gamelog.entries.push(format!(
+                                    "You use {} on {}, inflicting {} damage",
+                                    item_name.name, mob_name.name, damage.damage
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Status items land on their targets, or on the user when they
+            // are not aimed at all
+            let status_effect = inflicts_status.get(useitem.item);
+            match status_effect {
+                None => {}
+                Some(inflicts) => {
+                    let affected = if useitem.target.is_some() {
+                        targets.clone()
+                    } else {
+                        vec![entity]
+                    };
+                    for mob in affected.iter() {
+                        StatusEffects::apply(&mut statuses, *mob, inflicts.effect);
+                        if entity == *player_entity {
+                            if *mob == entity {
+                                gamelog
+                                    .entries
+                                    .push(format!("You are {}.", inflicts.effect.kind.adjective()));
+                            } else {
+                                gamelog.entries.push(format!(
+                                    "The {} is {}.",
+                                    names.get(*mob).unwrap().name,
+                                    inflicts.effect.kind.adjective()
+                                ));
+                            }
                         }
                     }
                 }
             }
+
+            let can_equip = equippable.get(useitem.item);
+            match can_equip {
+                None => {}
+                Some(can_equip) => {
+                    let target_slot = can_equip.slot;
+
+                    // Whatever already fills the slot goes back into the backpack
+                    let mut to_unequip: Vec<Entity> = Vec::new();
+                    for (item_entity, already_equipped, name) in
+                        (&entities, &equipped, &names).join()
+                    {
+                        if already_equipped.owner == entity && already_equipped.slot == target_slot
+                        {
+                            to_unequip.push(item_entity);
+                            if entity == *player_entity {
+                                gamelog
+                                    .entries
+                                    .push(format!("You unequip the {}.", name.name));
+                            }
+                        }
+                    }
+                    for item in to_unequip.iter() {
+                        equipped.remove(*item);
+                        backpack
+                            .insert(*item, InBackpack { owner: entity })
+                            .expect("Unable to insert backpack entry");
+                    }
+
+                    equipped
+                        .insert(
+                            useitem.item,
+                            Equipped {
+                                owner: entity,
+                                slot: target_slot,
+                            },
+                        )
+                        .expect("Unable to insert equipped component");
+                    backpack.remove(useitem.item);
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You equip the {}.",
+                            names.get(useitem.item).unwrap().name
+                        ));
+                    }
+                }
+            }
         }
 
         wants_use_item.clear();
@@ -180,3 +380,45 @@ impl<'a> System<'a> for ItemDropSystem {
         wants_drop.clear();
     }
 }
+
+pub struct ItemRemoveSystem {}
+
+impl<'a> System<'a> for ItemRemoveSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToRemoveItem>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, InBackpack>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_remove,
+            names,
+            mut equipped,
+            mut backpack,
+        ) = data;
+
+        for (entity, to_remove) in (&entities, &wants_remove).join() {
+            equipped.remove(to_remove.item);
+            backpack
+                .insert(to_remove.item, InBackpack { owner: entity })
+                .expect("Unable to insert backpack entry");
+
+            if entity == *player_entity {
+                gamelog.entries.push(format!(
+                    "You unequip the {}.",
+                    names.get(to_remove.item).unwrap().name
+                ));
+            }
+        }
+
+        wants_remove.clear();
+    }
+}
@@ -1,64 +1,255 @@
 use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
 
 mod ai;
+mod bench;
+mod camera;
 mod components;
 #[allow(dead_code)]
 mod constants;
 mod damage_system;
+mod door_system;
 mod gamelog;
 mod generator;
+mod headless;
+mod hunger_system;
+mod input;
 mod inventory_system;
 mod item_listing_system;
 mod map;
+mod map_builders;
 mod map_indexing;
 mod melee_system;
+mod options;
 mod player;
+mod random_table;
+mod ranged_combat_system;
+mod raws;
 mod rect;
+mod replay;
+mod saveload_system;
+mod seed;
+mod status_system;
+mod trap_system;
 mod ui;
 mod visibility;
 
-use ai::MonsterAI;
+use ai::{MonsterAI, Pathing};
+use camera::Camera;
 use components::*;
 use constants::*;
 use damage_system::DamageSystem;
+use door_system::DoorSystem;
 use gamelog::GameLog;
+use hunger_system::HungerSystem;
+use input::Input;
 use inventory_system::*;
 use item_listing_system::ItemListingSystem;
-use map::{draw_map, Map};
+use map::{draw_map, Map, TileType};
+use map_builders::BuilderKind;
 use map_indexing::MapIndexingSystem;
 use melee_system::MeleeCombatSystem;
-use player::player_input;
-use rect::Rect;
+use player::{player_input, Activity};
+use ranged_combat_system::RangedCombatSystem;
+use raws::RawMaster;
+use seed::RunSeed;
+use status_system::StatusEffectSystem;
+use trap_system::TriggerSystem;
 use visibility::VisibilitySystem;
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum RunState {
+    MainMenu {
+        menu_selection: ui::MainMenuSelection,
+    },
+    SaveGame,
     AwaitingInput,
     PreRun,
     PlayerTurn,
     MonsterTurn,
+    // Hasted monsters act a second time before the turn is over
+    HastedMonsterTurn,
     ShowInventory,
     ShowDropItem,
     ShowCharacter,
-    ShowTargeting { range: i32, item: Entity },
+    // Never saved, see resumable
+    #[serde(skip)]
+    ShowTargeting {
+        range: i32,
+        item: Entity,
+    },
+    NextLevel,
+    MapGeneration {
+        step: usize,
+    },
+    // Sweeps a magic map into view one row at a time
+    MagicMapReveal {
+        row: i32,
+    },
+    // Takes the next turn of a command that goes on for many, such as
+    // resting or exploring
+    Continuing,
     Dead,
 }
 
+impl RunState {
+    // States that wait on the player, as opposed to the ones that advance the
+    // simulation on their own
+    pub fn awaits_input(&self) -> bool {
+        matches!(
+            self,
+            RunState::MainMenu { .. }
+                | RunState::AwaitingInput
+                | RunState::ShowInventory
+                | RunState::ShowDropItem
+                | RunState::ShowCharacter
+                | RunState::ShowTargeting { .. }
+                | RunState::Dead
+        )
+    }
+
+    // The state a saved game picks up from. Menus and target selection are
+    // not part of the run, so a game saved in one resumes waiting for input.
+    pub fn resumable(self) -> RunState {
+        match self {
+            RunState::MainMenu { .. }
+            | RunState::SaveGame
+            | RunState::ShowInventory
+            | RunState::ShowDropItem
+            | RunState::ShowCharacter
+            | RunState::ShowTargeting { .. }
+            | RunState::MapGeneration { .. }
+            | RunState::Continuing => RunState::AwaitingInput,
+            _ => self,
+        }
+    }
+}
+
 pub struct State {
     ecs: World,
+    // Headless runs must not touch the player's save file
+    saves_enabled: bool,
+    recorder: Option<replay::Recorder>,
+    playback: Option<replay::Playback>,
+    // Generator for every level, or None to pick one at random each time
+    map_builder: Option<BuilderKind>,
+    // How the current level was generated, played back before it when
+    // visualize_mapgen is set
+    visualize_mapgen: bool,
+    mapgen_history: Vec<Map>,
+    mapgen_next_state: RunState,
+    // Resting or exploring, carried on turn after turn until it is done
+    activity: Option<Activity>,
+}
+
+// Every component the game uses, registered on a fresh world
+fn register_components(ecs: &mut World) {
+    ecs.register::<Ammunition>();
+    ecs.register::<AreaOfEffect>();
+    ecs.register::<Behaviour>();
+    ecs.register::<BlocksTile>();
+    ecs.register::<BlocksVisibility>();
+    ecs.register::<Clairvoyance>();
+    ecs.register::<CombatStats>();
+    ecs.register::<Consumable>();
+    ecs.register::<DefenseBonus>();
+    ecs.register::<Door>();
+    ecs.register::<EntityMoved>();
+    ecs.register::<EntryTrigger>();
+    ecs.register::<Equippable>();
+    ecs.register::<Equipped>();
+    ecs.register::<HealEffect>();
+    ecs.register::<Hidden>();
+    ecs.register::<HungerClock>();
+    ecs.register::<InBackpack>();
+    ecs.register::<InflictsDamage>();
+    ecs.register::<InflictsStatus>();
+    ecs.register::<Item>();
+    ecs.register::<Key>();
+    ecs.register::<MagicMapper>();
+    ecs.register::<MeleePowerBonus>();
+    ecs.register::<Monster>();
+    ecs.register::<Name>();
+    ecs.register::<Player>();
+    ecs.register::<PlayerMemory>();
+    ecs.register::<Position>();
+    ecs.register::<ProvidesFood>();
+    ecs.register::<RaisesAlarm>();
+    ecs.register::<Ranged>();
+    ecs.register::<RangedWeapon>();
+    ecs.register::<Renderable>();
+    ecs.register::<SingleActivation>();
+    ecs.register::<StatusEffects>();
+    ecs.register::<SufferDamage>();
+    ecs.register::<TeleportsTarget>();
+    ecs.register::<Viewshed>();
+    ecs.register::<WantsToDisplayContent>();
+    ecs.register::<WantsToDropItem>();
+    ecs.register::<WantsToMelee>();
+    ecs.register::<WantsToOpenDoor>();
+    ecs.register::<WantsToPickupItem>();
+    ecs.register::<WantsToRemoveItem>();
+    ecs.register::<WantsToShoot>();
+    ecs.register::<WantsToUseItem>();
+    ecs.register::<SimpleMarker<SerializeMe>>();
+    ecs.register::<SerializationHelper>();
 }
 
 impl State {
+    // Builds a fresh game on the first level, ready to run from PreRun
+    fn new(seed: u64, raws: RawMaster, map_builder: Option<BuilderKind>) -> State {
+        let mut gs = State {
+            ecs: World::new(),
+            saves_enabled: true,
+            recorder: None,
+            playback: None,
+            map_builder,
+            visualize_mapgen: false,
+            mapgen_history: Vec::new(),
+            mapgen_next_state: RunState::PreRun,
+            activity: None,
+        };
+        register_components(&mut gs.ecs);
+
+        gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+        gs.ecs.insert(RandomNumberGenerator::seeded(seed));
+        gs.ecs.insert(RunSeed { seed });
+        gs.ecs.insert(raws);
+
+        let (player_x, player_y) = gs.generate_level(1);
+        let player_entity = generator::spawn_player(&mut gs.ecs, player_x, player_y);
+        gs.ecs.insert(player_entity);
+        gs.ecs.insert(RunState::PreRun);
+        gs.ecs.insert(gamelog::GameLog {
+            entries: vec!["Welcome, traveller.".to_string()],
+        });
+
+        gs
+    }
+
     fn run_systems(&mut self) {
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
-        let mut mob = MonsterAI {};
+        let mut mob = MonsterAI {
+            pathing: Pathing::FlowMaps,
+        };
         mob.run_now(&self.ecs);
+        let mut triggers = TriggerSystem {};
+        triggers.run_now(&self.ecs);
+        let mut status = StatusEffectSystem {};
+        status.run_now(&self.ecs);
+        let mut doors = DoorSystem {};
+        doors.run_now(&self.ecs);
+        let mut hunger = HungerSystem {};
+        hunger.run_now(&self.ecs);
         let mut map_index = MapIndexingSystem {};
         map_index.run_now(&self.ecs);
         let mut melee = MeleeCombatSystem {};
         melee.run_now(&self.ecs);
+        let mut ranged = RangedCombatSystem {};
+        ranged.run_now(&self.ecs);
         let mut damage = DamageSystem {};
         damage.run_now(&self.ecs);
         let mut pickup = ItemCollectionSystem {};
@@ -69,34 +260,195 @@ impl State {
         items.run_now(&self.ecs);
         let mut drop = ItemDropSystem {};
         drop.run_now(&self.ecs);
+        let mut item_remove = ItemRemoveSystem {};
+        item_remove.run_now(&self.ecs);
 
         self.ecs.maintain();
     }
-}
 
-impl GameState for State {
-    fn tick(&mut self, ctx: &mut BTerm) {
-        ctx.cls();
+    // Builds and fills the map for `depth`, and returns where the player
+    // arrives on it
+    fn generate_level(&mut self, depth: i32) -> (i32, i32) {
+        let builder = {
+            let mut rng = self.ecs.write_resource::<RandomNumberGenerator>();
+            let kind = match self.map_builder {
+                Some(kind) => kind,
+                None => BuilderKind::random(&mut rng),
+            };
+            let mut builder = kind.builder(depth);
+            builder.build_map(&mut rng);
+            builder
+        };
+
+        let map = builder.map();
+        let (player_x, player_y) = builder.starting_position();
+        generator::spawn_regions(&mut self.ecs, &builder.spawn_regions(), depth);
+        generator::spawn_doors(
+            &mut self.ecs,
+            &map,
+            (player_x, player_y),
+            &builder.door_positions(),
+            depth,
+        );
+
+        self.mapgen_history = builder.snapshot_history();
+        self.ecs.insert(map);
+        self.ecs.insert(Point::new(player_x, player_y));
+
+        // Index the new contents straight away, so closed doors already
+        // block sight when the level is first seen
+        let mut map_index = MapIndexingSystem {};
+        map_index.run_now(&self.ecs);
+
+        (player_x, player_y)
+    }
+
+    // Once a turn is over, back to the player, or on with whatever they are
+    // in the middle of
+    fn after_turn(&self) -> RunState {
+        match self.activity {
+            Some(_) => RunState::Continuing,
+            None => RunState::AwaitingInput,
+        }
+    }
+
+    // Goes to `next` once the generation of the level has been shown, if it
+    // is to be
+    fn start_map_generation(&mut self, next: RunState) -> RunState {
+        if self.visualize_mapgen && !self.mapgen_history.is_empty() {
+            self.mapgen_next_state = next;
+            RunState::MapGeneration { step: 0 }
+        } else {
+            next
+        }
+    }
 
-        draw_map(&self.ecs, ctx);
+    // Everything except the player and what they carry or wear is left behind
+    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
+        let entities = self.ecs.entities();
+        let players = self.ecs.read_storage::<Player>();
+        let backpack = self.ecs.read_storage::<InBackpack>();
+        let equipped = self.ecs.read_storage::<Equipped>();
+        let player_entity = self.ecs.fetch::<Entity>();
+
+        entities
+            .join()
+            .filter(|entity| players.get(*entity).is_none())
+            .filter(|entity| match backpack.get(*entity) {
+                Some(pack) => pack.owner != *player_entity,
+                None => true,
+            })
+            .filter(|entity| match equipped.get(*entity) {
+                Some(equipped) => equipped.owner != *player_entity,
+                None => true,
+            })
+            .collect()
+    }
+
+    fn goto_next_level(&mut self) {
+        let to_delete = self.entities_to_remove_on_level_change();
+        for target in to_delete {
+            self.ecs
+                .delete_entity(target)
+                .expect("Unable to delete entity");
+        }
+
+        let new_depth = self.ecs.fetch::<Map>().depth + 1;
+        let (player_x, player_y) = self.generate_level(new_depth);
+
+        let player_entity = *self.ecs.fetch::<Entity>();
+        {
+            let mut positions = self.ecs.write_storage::<Position>();
+            if let Some(pos) = positions.get_mut(player_entity) {
+                pos.x = player_x;
+                pos.y = player_y;
+            }
+            let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+            if let Some(viewshed) = viewsheds.get_mut(player_entity) {
+                viewshed.dirty = true;
+            }
+        }
+
+        self.ecs
+            .fetch_mut::<GameLog>()
+            .entries
+            .push(format!("You descend to depth {}.", new_depth));
+    }
+
+    fn render(&self, ctx: &mut BTerm) {
+        let runstate = *self.ecs.fetch::<RunState>();
+
+        if let RunState::MainMenu { .. } = runstate {
+            ui::draw_main_menu(&self.ecs, ctx);
+            return;
+        }
+
+        if let RunState::MapGeneration { step } = runstate {
+            let snapshot = usize::min(
+                step / MAPGEN_FRAMES_PER_SNAPSHOT,
+                self.mapgen_history.len() - 1,
+            );
+            // The level being built is framed where the player will start
+            let map = &self.mapgen_history[snapshot];
+            draw_map(map, &Camera::new(map, *self.ecs.fetch::<Point>()), ctx);
+            return;
+        }
+
+        let camera = Camera::of_player(&self.ecs);
+        draw_map(&self.ecs.fetch::<Map>(), &camera, ctx);
 
         {
+            let player_entity = *self.ecs.fetch::<Entity>();
+            let telepathic =
+                status_system::has_status(&self.ecs, player_entity, StatusKind::Telepathy);
+            let entities = self.ecs.entities();
             let positions = self.ecs.read_storage::<Position>();
             let renderables = self.ecs.read_storage::<Renderable>();
+            let hidden = self.ecs.read_storage::<Hidden>();
+            let monsters = self.ecs.read_storage::<Monster>();
             let map = self.ecs.fetch::<Map>();
 
-            let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-            data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
+            let mut data = (&entities, &positions, &renderables, !&hidden)
+                .join()
+                .map(|(entity, pos, render, _)| (entity, pos, render))
+                .collect::<Vec<_>>();
+            data.sort_by(|&a, &b| b.2.render_order.cmp(&a.2.render_order));
 
-            for (pos, render) in data.iter() {
-                if map.visible_tiles[pos.x as usize][pos.y as usize] {
-                    ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph);
+            // Telepathy senses monsters wherever they are
+            for (entity, pos, render) in data.iter() {
+                let sensed = telepathic && monsters.contains(*entity);
+                if sensed || map.visible_tiles[pos.x as usize][pos.y as usize] {
+                    if let Some((x, y)) = camera.to_screen(pos.x, pos.y) {
+                        ctx.set(x, y, render.fg, render.bg, render.glyph);
+                    }
                 }
             }
+        }
 
-            ui::draw_ui(&self.ecs, ctx);
+        if let Some(Activity::Travelling { path, .. }) = &self.activity {
+            ui::draw_travel_path(&self.ecs, ctx, path);
         }
 
+        ui::draw_ui(&self.ecs, ctx);
+
+        match runstate {
+            RunState::AwaitingInput => ui::draw_tooltips(&self.ecs, ctx),
+            RunState::ShowInventory => ui::draw_inventory(&self.ecs, ctx),
+            RunState::ShowDropItem => ui::draw_drop_menu(&self.ecs, ctx),
+            RunState::ShowCharacter => ui::draw_character(&self.ecs, ctx),
+            RunState::ShowTargeting { range, item } => {
+                ui::draw_ranged_target(&self.ecs, ctx, range, item);
+                ui::draw_tooltips(&self.ecs, ctx);
+            }
+            RunState::Dead => ui::draw_game_over(&self.ecs, ctx),
+            _ => {}
+        }
+    }
+
+    // Advances the game by one frame, given what the player did during it.
+    // Returns true when the game asks to quit.
+    fn update(&mut self, input: &Input) -> bool {
+        let mut quit = false;
         let mut new_runstate;
         {
             let runstate = self.ecs.fetch::<RunState>();
@@ -104,33 +456,107 @@ impl GameState for State {
         }
 
         match new_runstate {
+            RunState::MainMenu { .. } => {
+                let result = ui::main_menu(self, input);
+                match result {
+                    ui::MainMenuResult::NoSelection { selected } => {
+                        new_runstate = RunState::MainMenu {
+                            menu_selection: selected,
+                        }
+                    }
+                    ui::MainMenuResult::Selected { selected } => match selected {
+                        ui::MainMenuSelection::NewGame => {
+                            new_runstate = self.start_map_generation(RunState::PreRun)
+                        }
+                        ui::MainMenuSelection::Continue => {
+                            match saveload_system::load_game(&mut self.ecs) {
+                                Ok(saved_runstate) => {
+                                    // Rebuild the map index before handing
+                                    // the game back where it was saved
+                                    self.run_systems();
+                                    self.ecs.maintain();
+                                    new_runstate = saved_runstate;
+                                }
+                                Err(e) => {
+                                    self.ecs
+                                        .fetch_mut::<GameLog>()
+                                        .entries
+                                        .push(format!("Unable to load saved game: {}.", e));
+                                    new_runstate = RunState::PreRun;
+                                }
+                            }
+                        }
+                        ui::MainMenuSelection::Quit => quit = true,
+                    },
+                }
+            }
+            RunState::SaveGame => {
+                // Escape is only read while the game waits for input, so
+                // that is where it resumes
+                if self.saves_enabled {
+                    saveload_system::save_game(&mut self.ecs, RunState::AwaitingInput);
+                }
+                quit = true;
+            }
             RunState::PreRun => {
                 self.run_systems();
                 self.ecs.maintain();
                 new_runstate = RunState::AwaitingInput;
             }
             RunState::AwaitingInput => {
-                new_runstate = player_input(self, ctx);
+                new_runstate = player_input(self, input);
             }
             RunState::PlayerTurn => {
                 self.run_systems();
                 self.ecs.maintain();
-                new_runstate = RunState::MonsterTurn;
+                let magic_mapping = matches!(
+                    *self.ecs.fetch::<RunState>(),
+                    RunState::MagicMapReveal { .. }
+                );
+                if magic_mapping {
+                    new_runstate = RunState::MagicMapReveal { row: 0 };
+                } else if status_system::player_extra_action(&mut self.ecs) {
+                    new_runstate = self.after_turn();
+                } else {
+                    new_runstate = RunState::MonsterTurn;
+                }
             }
             RunState::MonsterTurn => {
                 self.run_systems();
                 self.ecs.maintain();
-                new_runstate = RunState::AwaitingInput;
+                if status_system::monster_extra_actions(&self.ecs) {
+                    new_runstate = RunState::HastedMonsterTurn;
+                } else {
+                    new_runstate = self.after_turn();
+                }
+            }
+            RunState::HastedMonsterTurn => {
+                self.run_systems();
+                self.ecs.maintain();
+                new_runstate = self.after_turn();
+            }
+            RunState::Continuing => {
+                new_runstate = player::continue_activity(self);
             }
             RunState::ShowInventory => {
-                let result = ui::show_inventory(self, ctx);
+                let result = ui::show_inventory(self, input);
                 match result {
                     (ui::ItemMenuResult::Cancel, _) => new_runstate = RunState::AwaitingInput,
                     (ui::ItemMenuResult::NoResponse, _) | (ui::ItemMenuResult::Selected, None) => {}
                     (ui::ItemMenuResult::Selected, Some(entity)) => {
                         let ranged_list = self.ecs.read_storage::<Ranged>();
                         let is_ranged_item = ranged_list.get(entity);
-                        if let Some(is_ranged_item) = is_ranged_item {
+                        let is_equipped = self.ecs.read_storage::<Equipped>().contains(entity);
+                        if is_equipped {
+                            let mut intent = self.ecs.write_storage::<WantsToRemoveItem>();
+                            intent
+                                .insert(
+                                    *self.ecs.fetch::<Entity>(),
+                                    WantsToRemoveItem { item: entity },
+                                )
+                                .expect("Unable to insert intent");
+                            new_runstate = RunState::PlayerTurn;
+                        } else if let Some(is_ranged_item) = is_ranged_item {
                             new_runstate = RunState::ShowTargeting {
                                 range: is_ranged_item.range,
                                 item: entity,
@@ -152,7 +578,7 @@ impl GameState for State {
                 }
             }
             RunState::ShowDropItem => {
-                let result = ui::show_drop_menu(self, ctx);
+                let result = ui::show_drop_menu(self, input);
                 match result {
                     (ui::ItemMenuResult::Cancel, _) => new_runstate = RunState::AwaitingInput,
                     (ui::ItemMenuResult::NoResponse, _) | (ui::ItemMenuResult::Selected, None) => {}
@@ -169,17 +595,27 @@ impl GameState for State {
                 }
             }
             RunState::ShowCharacter => {
-                let result = ui::show_character(self, ctx);
+                let result = ui::show_character(self, input);
                 match result {
                     ui::CharacterMenuResult::Cancel => new_runstate = RunState::AwaitingInput,
                     _ => {}
                 }
             }
             RunState::ShowTargeting { range, item } => {
-                let target = ui::ranged_target(self, ctx, range);
+                let target = ui::ranged_target(self, input, range);
                 match target {
                     (ui::ItemMenuResult::Cancel, _) => new_runstate = RunState::AwaitingInput,
                     (ui::ItemMenuResult::NoResponse, _) => {}
+                    // Targeting with a bow fires it rather than using it
+                    (ui::ItemMenuResult::Selected, Some(target))
+                        if self.ecs.read_storage::<RangedWeapon>().contains(item) =>
+                    {
+                        let mut intent = self.ecs.write_storage::<WantsToShoot>();
+                        intent
+                            .insert(*self.ecs.fetch::<Entity>(), WantsToShoot { target })
+                            .expect("Unable to insert intent");
+                        new_runstate = RunState::PlayerTurn;
+                    }
                     (ui::ItemMenuResult::Selected, target) => {
                         let mut intent = self.ecs.write_storage::<WantsToUseItem>();
                         intent
@@ -189,7 +625,36 @@ impl GameState for State {
                     }
                 }
             }
-            RunState::Dead => {}
+            RunState::NextLevel => {
+                self.goto_next_level();
+                new_runstate = self.start_map_generation(RunState::PreRun);
+            }
+            RunState::MapGeneration { step } => {
+                let last_step = self.mapgen_history.len() * MAPGEN_FRAMES_PER_SNAPSHOT;
+                if input.key.is_some() || step + 1 >= last_step {
+                    new_runstate = self.mapgen_next_state;
+                } else {
+                    new_runstate = RunState::MapGeneration { step: step + 1 };
+                }
+            }
+            RunState::MagicMapReveal { row } => {
+                let mut map = self.ecs.fetch_mut::<Map>();
+                for x in 0..map.width {
+                    map.revealed_tiles[x as usize][row as usize] = true;
+                }
+                if row >= map.height - 1 {
+                    new_runstate = RunState::MonsterTurn;
+                } else {
+                    new_runstate = RunState::MagicMapReveal { row: row + 1 };
+                }
+            }
+            RunState::Dead => {
+                self.activity = None;
+                let result = ui::game_over(self, input);
+                if result == ui::GameOverResult::Quit {
+                    quit = true;
+                }
+            }
         }
 
         {
@@ -198,50 +663,113 @@ impl GameState for State {
         }
 
         damage_system::delete_dead(&mut self.ecs);
+
+        // Death is permanent
+        if self.saves_enabled
+            && new_runstate != RunState::Dead
+            && *self.ecs.fetch::<RunState>() == RunState::Dead
+        {
+            saveload_system::delete_save();
+        }
+
+        quit
+    }
+}
+
+impl GameState for State {
+    fn tick(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.render(ctx);
+
+        let steps = match &self.playback {
+            Some(playback) => playback.speed,
+            None => 1,
+        };
+        for _ in 0..steps {
+            let awaits_input = self.ecs.fetch::<RunState>().awaits_input();
+            let input = match &mut self.playback {
+                Some(playback) if awaits_input => playback.next_input(),
+                Some(_) => Input::default(),
+                None => {
+                    let input = Input::from_context(ctx);
+                    if let (Some(recorder), true) = (&mut self.recorder, awaits_input) {
+                        recorder.record(&input);
+                    }
+                    input
+                }
+            };
+
+            if self.update(&input) {
+                ctx.quit();
+                return;
+            }
+
+            if matches!(&self.playback, Some(playback) if playback.finished()) {
+                self.playback = None;
+                self.ecs
+                    .fetch_mut::<GameLog>()
+                    .entries
+                    .push("The replay is over, you are in control.".to_string());
+                break;
+            }
+        }
     }
 }
 
 fn main() -> BError {
+    let options = options::Options::from_args()?;
+    let seed = options
+        .seed
+        .unwrap_or_else(|| RandomNumberGenerator::new().next_u64());
+
+    let raws = match &options.raws {
+        Some(path) => RawMaster::from_file(path)?,
+        None => RawMaster::embedded()?,
+    };
+
+    if let Some(max_depth) = options.spawn_report {
+        print!("{}", raws.spawn_report(max_depth));
+        return Ok(());
+    }
+
+    if let Some(monsters) = options.bench_pathing {
+        bench::run(seed, monsters, &raws, options.map_builder);
+        return Ok(());
+    }
+
+    if let Some(script) = &options.headless {
+        return headless::run(script, seed, options.games, &raws, options.map_builder)
+            .map_err(|e| e.into());
+    }
+
     let context = BTermBuilder::simple80x50().with_title("Explore").build()?;
-    let mut gs = State { ecs: World::new() };
-    gs.ecs.register::<BlocksTile>();
-    gs.ecs.register::<CombatStats>();
-    gs.ecs.register::<Consumable>();
-    gs.ecs.register::<HealEffect>();
-    gs.ecs.register::<InBackpack>();
-    gs.ecs.register::<InflictsDamage>();
-    gs.ecs.register::<Item>();
-    gs.ecs.register::<Monster>();
-    gs.ecs.register::<Name>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Ranged>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<SufferDamage>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<WantsToDisplayContent>();
-    gs.ecs.register::<WantsToDropItem>();
-    gs.ecs.register::<WantsToMelee>();
-    gs.ecs.register::<WantsToPickupItem>();
-    gs.ecs.register::<WantsToUseItem>();
-
-    gs.ecs.insert(RandomNumberGenerator::new());
-
-    let map = Map::new_map(MAP_X, MAP_Y);
-    let (player_x, player_y) = map.rooms[0].center();
-    let player_entity = generator::spawn_player(&mut gs.ecs, player_x, player_y);
-
-    gs.ecs.insert(player_entity);
-
-    for room in map.rooms.iter().skip(1) {
-        generator::spawn_room_contents(&mut gs.ecs, room);
-    }
-    gs.ecs.insert(map);
-    gs.ecs.insert(Point::new(player_x, player_y));
-    gs.ecs.insert(RunState::PreRun);
-    gs.ecs.insert(gamelog::GameLog {
-        entries: vec!["Welcome, traveller.".to_string()],
-    });
+
+    // Recorded and replayed games skip the menu, so every input they hold
+    // belongs to the run itself
+    let mut gs = if let Some(path) = &options.replay {
+        let playback = replay::Playback::open(path, options.replay_speed)?;
+        let mut gs = State::new(playback.seed, raws, playback.map_builder);
+        gs.saves_enabled = false;
+        gs.playback = Some(playback);
+        gs
+    } else if let Some(path) = &options.record {
+        let mut gs = State::new(seed, raws, options.map_builder);
+        gs.recorder = Some(replay::Recorder::create(path, seed, options.map_builder)?);
+        gs
+    } else {
+        let mut gs = State::new(seed, raws, options.map_builder);
+        gs.ecs.insert(RunState::MainMenu {
+            menu_selection: ui::MainMenuSelection::NewGame,
+        });
+        gs
+    };
+
+    gs.visualize_mapgen = options.visualize_mapgen;
+    let runstate = *gs.ecs.fetch::<RunState>();
+    if runstate == RunState::PreRun {
+        let runstate = gs.start_map_generation(RunState::PreRun);
+        gs.ecs.insert(runstate);
+    }
 
     main_loop(context, gs)
 }
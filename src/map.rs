@@ -1,29 +1,52 @@
+use crate::camera::Camera;
 use crate::constants::{
-    BASE_BG_COLOR, FLOOR_COLOR, FLOOR_COLOR_OOS, MAP_X, MAP_Y, WALL_COLOR, WALL_COLOR_OOS,
+    BASE_BG_COLOR, FLOOR_COLOR, FLOOR_COLOR_OOS, MAP_X, MAP_Y, STAIRS_COLOR, STAIRS_COLOR_OOS,
+    WALL_COLOR, WALL_COLOR_OOS,
 };
-use crate::rect::Rect;
 use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
+    DownStairs,
 }
 
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Map {
     pub tiles: Vec<Vec<TileType>>,
-    pub rooms: Vec<Rect>,
     pub width: i32,
     pub height: i32,
+    pub depth: i32,
     pub revealed_tiles: Vec<Vec<bool>>,
     pub visible_tiles: Vec<Vec<bool>>,
     pub blocked: Vec<Vec<bool>>,
+    // Tiles whose contents, such as closed doors, cannot be seen through
+    pub view_blocked: Vec<Vec<bool>>,
+
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
     pub tile_content: Vec<Vec<Vec<Entity>>>,
 }
 
 impl Map {
+    // Solid wall everywhere, for a map builder to carve into
+    pub fn new(depth: i32) -> Map {
+        Map {
+            tiles: vec![vec![TileType::Wall; MAP_Y as usize]; MAP_X as usize],
+            width: MAP_X,
+            height: MAP_Y,
+            depth,
+            revealed_tiles: vec![vec![false; MAP_Y as usize]; MAP_X as usize],
+            visible_tiles: vec![vec![false; MAP_Y as usize]; MAP_X as usize],
+            blocked: vec![vec![false; MAP_Y as usize]; MAP_X as usize],
+            view_blocked: vec![vec![false; MAP_Y as usize]; MAP_X as usize],
+            tile_content: vec![vec![Vec::new(); MAP_Y as usize]; MAP_X as usize],
+        }
+    }
+
     pub fn xy_idx(&self, x: i32, y: i32) -> usize {
         (y * self.width) as usize + x as usize
     }
@@ -32,101 +55,77 @@ impl Map {
         (idx % self.width as usize, idx / self.width as usize)
     }
 
-    fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
-        for x in min(x1, x2)..=max(x1, x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < (MAP_X * MAP_Y) as usize {
-                self.tiles[x as usize][y as usize] = TileType::Floor;
-            }
+    fn is_exit_valid(&self, x: i32, y: i32) -> bool {
+        if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 {
+            return false;
         }
+        !self.blocked[x as usize][y as usize]
     }
 
-    fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
-        for y in min(y1, y2)..=max(y1, y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < (MAP_X * MAP_Y) as usize {
-                self.tiles[x as usize][y as usize] = TileType::Floor;
-            }
-        }
+    // Diagonal steps may not cut between two wall corners
+    pub fn squeezes_between_walls(&self, x: i32, y: i32, delta_x: i32, delta_y: i32) -> bool {
+        delta_x != 0
+            && delta_y != 0
+            && self.tiles[(x + delta_x) as usize][y as usize] == TileType::Wall
+            && self.tiles[x as usize][(y + delta_y) as usize] == TileType::Wall
     }
 
-    fn apply_room_to_map(&mut self, room: &Rect) {
-        // If room is out of bounds, do not render it
-        if room.out_of_bounds(self) {
-            return;
-        }
+    // Rebuilds the (unsaved) per-tile entity index after a load
+    pub fn reset_content(&mut self) {
+        self.tile_content = vec![vec![Vec::new(); self.height as usize]; self.width as usize];
+    }
 
-        for y in room.y1 + 1..=room.y2 {
-            for x in room.x1 + 1..=room.x2 {
-                self.tiles[x as usize][y as usize] = TileType::Floor;
+    pub fn clear_content(&mut self) {
+        for line in self.tile_content.iter_mut() {
+            for content in line.iter_mut() {
+                content.clear();
             }
         }
     }
 
-    pub fn new_map(max_x: i32, max_y: i32) -> Map {
-        const MAX_ROOMS: i32 = 40;
-        const MIN_SIZE: i32 = 6;
-        const MAX_SIZE: i32 = 10;
-
-        let mut map = Map {
-            tiles: vec![vec![TileType::Wall; max_y as usize]; max_x as usize],
-            rooms: Vec::new(),
-            width: MAP_X,
-            height: MAP_Y,
-            revealed_tiles: vec![vec![false; max_y as usize]; max_x as usize],
-            visible_tiles: vec![vec![false; max_y as usize]; max_x as usize],
-            blocked: vec![vec![false; max_y as usize]; max_x as usize],
-            tile_content: vec![vec![Vec::new(); max_y as usize]; max_x as usize],
-        };
-
-        let mut rng = RandomNumberGenerator::new();
-
-        for _ in 0..MAX_ROOMS {
-            let w = rng.range(MIN_SIZE, MAX_SIZE);
-            let h = rng.range(MIN_SIZE, MAX_SIZE);
-            let x = rng.roll_dice(1, map.width - w - 1) - 1;
-            let y = rng.roll_dice(1, map.height - h - 1) - 1;
-            let new_room = Rect::new(x, y, w, h);
-            if map
-                .rooms
-                .iter()
-                .all(|other_room| !new_room.intersect(&other_room))
-                && !new_room.out_of_bounds(&map)
-            {
-                map.apply_room_to_map(&new_room);
-
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-                    if rng.range(0, 2) == 1 {
-                        map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    }
-                }
-
-                map.rooms.push(new_room);
-            }
-        }
+    // Tiles caught in a blast centred on `center`: everything within `radius`
+    // that the centre has line of sight to, so walls shelter what is behind
+    pub fn blast_area(&self, center: Point, radius: i32) -> Vec<Point> {
+        let mut tiles = field_of_view(center, radius, self);
+        tiles.retain(|p| p.x >= 0 && p.x < self.width && p.y >= 0 && p.y < self.height);
+        tiles
+    }
 
-        map
+    // A depth no Dijkstra map over this level can reach, so none of them stops
+    // short: even a path through every tile, all in diagonal steps, costs less
+    pub fn dijkstra_max_depth(&self) -> f32 {
+        (self.width * self.height) as f32 * std::f32::consts::SQRT_2
     }
 
-    fn is_exit_valid(&self, x: i32, y: i32) -> bool {
-        if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 {
-            return false;
+    // Floor tiles that can be walked to from `start` without crossing a wall
+    // or any of the `obstacles`
+    pub fn reachable_tiles(&self, start: (i32, i32), obstacles: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        let mut map = self.clone();
+        map.populate_blocked();
+        for &(x, y) in obstacles.iter() {
+            map.blocked[x as usize][y as usize] = true;
         }
-        !self.blocked[x as usize][y as usize]
-    }
 
-    pub fn clear_content(&mut self) {
-        for line in self.tile_content.iter_mut() {
-            for content in line.iter_mut() {
-                content.clear();
+        let start_idx = map.xy_idx(start.0, start.1);
+        let distances = DijkstraMap::new(
+            map.width,
+            map.height,
+            &[start_idx],
+            &map,
+            map.dijkstra_max_depth(),
+        );
+
+        let mut tiles = Vec::new();
+        for x in 0..map.width {
+            for y in 0..map.height {
+                if map.tiles[x as usize][y as usize] == TileType::Floor
+                    && distances.map[map.xy_idx(x, y)] < f32::MAX
+                {
+                    tiles.push((x, y));
+                }
             }
         }
+        tiles
     }
 
     pub fn populate_blocked(&mut self) {
@@ -147,7 +146,7 @@ impl Algorithm2D for Map {
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
         let (x, y) = self.idx_xy(idx);
-        self.tiles[x][y] == TileType::Wall
+        self.tiles[x][y] == TileType::Wall || self.view_blocked[x][y]
     }
 
     fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
@@ -169,6 +168,15 @@ impl BaseMap for Map {
             exits.push((idx + w, 1.0))
         };
 
+        for (delta_x, delta_y) in [(-1, -1), (1, -1), (-1, 1), (1, 1)].iter() {
+            if self.is_exit_valid(x + delta_x, y + delta_y)
+                && !self.squeezes_between_walls(x, y, *delta_x, *delta_y)
+            {
+                let exit = self.xy_idx(x + delta_x, y + delta_y);
+                exits.push((exit, std::f32::consts::SQRT_2))
+            }
+        }
+
         exits
     }
 
@@ -180,9 +188,7 @@ impl BaseMap for Map {
     }
 }
 
-pub fn draw_map(ecs: &World, ctx: &mut BTerm) {
-    let map = ecs.fetch::<Map>();
-
+pub fn draw_map(map: &Map, camera: &Camera, ctx: &mut BTerm) {
     for (x, line) in map.tiles.iter().enumerate() {
         for (y, tile) in line.iter().enumerate() {
             if map.revealed_tiles[x][y] {
@@ -205,8 +211,24 @@ pub fn draw_map(ecs: &World, ctx: &mut BTerm) {
                             WALL_COLOR_OOS
                         };
                     }
+                    TileType::DownStairs => {
+                        glyph = to_cp437('>');
+                        fg = if map.visible_tiles[x][y] {
+                            STAIRS_COLOR
+                        } else {
+                            STAIRS_COLOR_OOS
+                        };
+                    }
+                }
+                if let Some((screen_x, screen_y)) = camera.to_screen(x as i32, y as i32) {
+                    ctx.set(
+                        screen_x,
+                        screen_y,
+                        RGB::named(fg),
+                        RGB::named(BASE_BG_COLOR),
+                        glyph,
+                    );
                 }
-                ctx.set(x, y, RGB::named(fg), RGB::named(BASE_BG_COLOR), glyph);
             }
         }
     }
@@ -0,0 +1,97 @@
+use super::common::{apply_room_to_map, connect, room_doors, room_regions, BuilderMap};
+use super::MapBuilder;
+use crate::map::TileType;
+use crate::rect::Rect;
+use bracket_lib::prelude::*;
+
+// Smallest side a space can be left with after being cut in two
+const MIN_LEAF_SIZE: i32 = 10;
+const MIN_ROOM_SIZE: i32 = 4;
+
+// Cuts the map in two again and again, then puts one room in every piece, so
+// rooms cover the whole map without ever overlapping
+pub struct BspBuilder {
+    build: BuilderMap,
+    rooms: Vec<Rect>,
+}
+
+impl BspBuilder {
+    pub fn new(depth: i32) -> BspBuilder {
+        BspBuilder {
+            build: BuilderMap::new(depth),
+            rooms: Vec::new(),
+        }
+    }
+
+    // Collects the pieces in tree order, so pieces that follow each other
+    // are neighbours
+    fn subdivide(&self, space: Rect, rng: &mut RandomNumberGenerator, leaves: &mut Vec<Rect>) {
+        let width = space.x2 - space.x1;
+        let height = space.y2 - space.y1;
+        let can_split_x = width >= MIN_LEAF_SIZE * 2;
+        let can_split_y = height >= MIN_LEAF_SIZE * 2;
+
+        // Cutting across the longer side keeps the pieces roughly square
+        if can_split_x && (width >= height || !can_split_y) {
+            let cut = rng.range(space.x1 + MIN_LEAF_SIZE, space.x2 - MIN_LEAF_SIZE + 1);
+            self.subdivide(Rect { x2: cut, ..space }, rng, leaves);
+            self.subdivide(Rect { x1: cut, ..space }, rng, leaves);
+        } else if can_split_y {
+            let cut = rng.range(space.y1 + MIN_LEAF_SIZE, space.y2 - MIN_LEAF_SIZE + 1);
+            self.subdivide(Rect { y2: cut, ..space }, rng, leaves);
+            self.subdivide(Rect { y1: cut, ..space }, rng, leaves);
+        } else {
+            leaves.push(space);
+        }
+    }
+}
+
+impl MapBuilder for BspBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut leaves = Vec::new();
+        let whole_map = Rect::new(1, 1, self.build.map.width - 3, self.build.map.height - 3);
+        self.subdivide(whole_map, rng, &mut leaves);
+
+        // Rooms stay a tile short of their piece's far edges, leaving a wall
+        // between neighbouring rooms
+        for leaf in leaves.iter() {
+            let leaf_w = leaf.x2 - leaf.x1;
+            let leaf_h = leaf.y2 - leaf.y1;
+            let w = rng.range(MIN_ROOM_SIZE, leaf_w - 1);
+            let h = rng.range(MIN_ROOM_SIZE, leaf_h - 1);
+            let x = leaf.x1 + rng.range(0, leaf_w - w);
+            let y = leaf.y1 + rng.range(0, leaf_h - h);
+            let room = Rect::new(x, y, w, h);
+            apply_room_to_map(&mut self.build.map, &room);
+            self.rooms.push(room);
+            self.build.take_snapshot();
+        }
+
+        for i in 1..self.rooms.len() {
+            let from = self.rooms[i - 1].center();
+            let to = self.rooms[i].center();
+            connect(&mut self.build.map, from, to, rng);
+            self.build.take_snapshot();
+        }
+
+        let (stairs_x, stairs_y) = self.rooms[self.rooms.len() - 1].center();
+        self.build.map.tiles[stairs_x as usize][stairs_y as usize] = TileType::DownStairs;
+        self.build.take_snapshot();
+    }
+
+    fn starting_position(&self) -> (i32, i32) {
+        self.rooms[0].center()
+    }
+
+    fn spawn_regions(&self) -> Vec<Vec<(i32, i32)>> {
+        room_regions(&self.rooms)
+    }
+
+    fn door_positions(&self) -> Vec<(i32, i32)> {
+        room_doors(&self.build.map, &self.rooms)
+    }
+
+    fn builder_map(&self) -> &BuilderMap {
+        &self.build
+    }
+}
@@ -0,0 +1,89 @@
+use super::common::{chunk_regions, cull_unreachable_and_place_stairs, nearest_floor, BuilderMap};
+use super::MapBuilder;
+use crate::map::TileType;
+use bracket_lib::prelude::*;
+
+const FLOOR_CHANCE: i32 = 45;
+const SMOOTHING_PASSES: i32 = 15;
+
+// Natural looking caves: random noise, smoothed over and over so each tile
+// ends up like most of its neighbours
+pub struct CellularAutomataBuilder {
+    build: BuilderMap,
+    starting_position: (i32, i32),
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(depth: i32) -> CellularAutomataBuilder {
+        CellularAutomataBuilder {
+            build: BuilderMap::new(depth),
+            starting_position: (0, 0),
+        }
+    }
+
+    fn walls_around(&self, x: i32, y: i32) -> i32 {
+        let mut walls = 0;
+        for delta_x in -1..=1 {
+            for delta_y in -1..=1 {
+                if (delta_x, delta_y) != (0, 0)
+                    && self.build.map.tiles[(x + delta_x) as usize][(y + delta_y) as usize]
+                        == TileType::Wall
+                {
+                    walls += 1;
+                }
+            }
+        }
+        walls
+    }
+}
+
+impl MapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        for x in 1..self.build.map.width - 1 {
+            for y in 1..self.build.map.height - 1 {
+                if rng.roll_dice(1, 100) <= FLOOR_CHANCE {
+                    self.build.map.tiles[x as usize][y as usize] = TileType::Floor;
+                }
+            }
+        }
+        self.build.take_snapshot();
+
+        // Crowded tiles fill in and lonely ones open up
+        for _ in 0..SMOOTHING_PASSES {
+            let mut tiles = self.build.map.tiles.clone();
+            for x in 1..self.build.map.width - 1 {
+                for y in 1..self.build.map.height - 1 {
+                    let walls = self.walls_around(x, y);
+                    tiles[x as usize][y as usize] = if walls > 4 || walls == 0 {
+                        TileType::Wall
+                    } else {
+                        TileType::Floor
+                    };
+                }
+            }
+            self.build.map.tiles = tiles;
+            self.build.take_snapshot();
+        }
+
+        let center = (self.build.map.width / 2, self.build.map.height / 2);
+        self.starting_position = nearest_floor(&mut self.build.map, center);
+        cull_unreachable_and_place_stairs(&mut self.build.map, self.starting_position);
+        self.build.take_snapshot();
+    }
+
+    fn starting_position(&self) -> (i32, i32) {
+        self.starting_position
+    }
+
+    fn spawn_regions(&self) -> Vec<Vec<(i32, i32)>> {
+        chunk_regions(&self.build.map, self.starting_position)
+    }
+
+    fn door_positions(&self) -> Vec<(i32, i32)> {
+        Vec::new()
+    }
+
+    fn builder_map(&self) -> &BuilderMap {
+        &self.build
+    }
+}
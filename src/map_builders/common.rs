@@ -0,0 +1,246 @@
+use crate::map::{Map, TileType};
+use crate::rect::Rect;
+use bracket_lib::prelude::*;
+use std::cmp::{max, min};
+
+// Builders without rooms split the map into squares of this size to spread
+// their spawns around
+const SPAWN_REGION_SIZE: i32 = 16;
+
+// The map a builder is working on, and a snapshot of it after each step, the
+// last being the finished map, so generation can be watched
+pub struct BuilderMap {
+    pub map: Map,
+    pub history: Vec<Map>,
+}
+
+impl BuilderMap {
+    pub fn new(depth: i32) -> BuilderMap {
+        BuilderMap {
+            map: Map::new(depth),
+            history: Vec::new(),
+        }
+    }
+
+    // Keeps a copy of the map as it stands, with every tile shown
+    pub fn take_snapshot(&mut self) {
+        let mut snapshot = self.map.clone();
+        for line in snapshot.revealed_tiles.iter_mut() {
+            line.fill(true);
+        }
+        for line in snapshot.visible_tiles.iter_mut() {
+            line.fill(true);
+        }
+        self.history.push(snapshot);
+    }
+}
+
+pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
+    // If room is out of bounds, do not render it
+    if room.out_of_bounds(map) {
+        return;
+    }
+
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            map.tiles[x as usize][y as usize] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        if x > 0 && x < map.width && y > 0 && y < map.height {
+            map.tiles[x as usize][y as usize] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        if x > 0 && x < map.width && y > 0 && y < map.height {
+            map.tiles[x as usize][y as usize] = TileType::Floor;
+        }
+    }
+}
+
+// Joins two points with an L-shaped corridor, bending one way or the other
+pub fn connect(map: &mut Map, from: (i32, i32), to: (i32, i32), rng: &mut RandomNumberGenerator) {
+    let ((from_x, from_y), (to_x, to_y)) = (from, to);
+    if rng.range(0, 2) == 1 {
+        apply_horizontal_tunnel(map, from_x, to_x, from_y);
+        apply_vertical_tunnel(map, from_y, to_y, to_x);
+    } else {
+        apply_vertical_tunnel(map, from_y, to_y, from_x);
+        apply_horizontal_tunnel(map, from_x, to_x, to_y);
+    }
+}
+
+// The inside of every room but the first, where the player starts
+pub fn room_regions(rooms: &[Rect]) -> Vec<Vec<(i32, i32)>> {
+    rooms
+        .iter()
+        .skip(1)
+        .map(|room| {
+            let mut tiles = Vec::new();
+            for y in room.y1 + 1..=room.y2 {
+                for x in room.x1 + 1..=room.x2 {
+                    tiles.push((x, y));
+                }
+            }
+            tiles
+        })
+        .collect()
+}
+
+// Gaps that corridors cut into the walls around rooms, where a door would sit
+// between two walls. Doors are never placed side by side.
+pub fn room_doors(map: &Map, rooms: &[Rect]) -> Vec<(i32, i32)> {
+    let is_wall = |x: i32, y: i32| map.tiles[x as usize][y as usize] == TileType::Wall;
+
+    let mut doors: Vec<(i32, i32)> = Vec::new();
+    for room in rooms.iter() {
+        let mut ring = Vec::new();
+        for x in room.x1..=room.x2 + 1 {
+            ring.push((x, room.y1));
+            ring.push((x, room.y2 + 1));
+        }
+        for y in room.y1 + 1..=room.y2 {
+            ring.push((room.x1, y));
+            ring.push((room.x2 + 1, y));
+        }
+
+        for (x, y) in ring {
+            if x < 1 || x >= map.width - 1 || y < 1 || y >= map.height - 1 {
+                continue;
+            }
+            if map.tiles[x as usize][y as usize] != TileType::Floor {
+                continue;
+            }
+
+            let across_x =
+                is_wall(x - 1, y) && is_wall(x + 1, y) && !is_wall(x, y - 1) && !is_wall(x, y + 1);
+            let across_y =
+                is_wall(x, y - 1) && is_wall(x, y + 1) && !is_wall(x - 1, y) && !is_wall(x + 1, y);
+            let next_to_door = doors
+                .iter()
+                .any(|&(door_x, door_y)| (door_x - x).abs() <= 1 && (door_y - y).abs() <= 1);
+            if (across_x || across_y) && !next_to_door {
+                doors.push((x, y));
+            }
+        }
+    }
+    doors
+}
+
+// The floor of each square of the map, except the one the player starts in
+pub fn chunk_regions(map: &Map, start: (i32, i32)) -> Vec<Vec<(i32, i32)>> {
+    let mut regions = Vec::new();
+    for chunk_x in (0..map.width).step_by(SPAWN_REGION_SIZE as usize) {
+        for chunk_y in (0..map.height).step_by(SPAWN_REGION_SIZE as usize) {
+            let contains_start = start.0 >= chunk_x
+                && start.0 < chunk_x + SPAWN_REGION_SIZE
+                && start.1 >= chunk_y
+                && start.1 < chunk_y + SPAWN_REGION_SIZE;
+            if contains_start {
+                continue;
+            }
+
+            let mut tiles = Vec::new();
+            for x in chunk_x..min(chunk_x + SPAWN_REGION_SIZE, map.width) {
+                for y in chunk_y..min(chunk_y + SPAWN_REGION_SIZE, map.height) {
+                    if map.tiles[x as usize][y as usize] == TileType::Floor {
+                        tiles.push((x, y));
+                    }
+                }
+            }
+            if !tiles.is_empty() {
+                regions.push(tiles);
+            }
+        }
+    }
+    regions
+}
+
+// The floor tile closest to `target` in the largest stretch of connected
+// floor, carving one there if the map has none. Everything the player cannot
+// walk to is culled afterwards, so starting in a small pocket would leave
+// little else of the level.
+pub fn nearest_floor(map: &mut Map, target: (i32, i32)) -> (i32, i32) {
+    // A single flood fill labels every stretch of floor, counting its tiles
+    map.populate_blocked();
+    let mut labels: Vec<Option<usize>> = vec![None; (map.width * map.height) as usize];
+    let mut sizes: Vec<usize> = Vec::new();
+    for start in 0..labels.len() {
+        let (x, y) = map.idx_xy(start);
+        if map.tiles[x][y] != TileType::Floor || labels[start].is_some() {
+            continue;
+        }
+        let label = sizes.len();
+        sizes.push(0);
+        labels[start] = Some(label);
+        let mut open = vec![start];
+        while let Some(idx) = open.pop() {
+            sizes[label] += 1;
+            for (exit, _cost) in map.get_available_exits(idx) {
+                if labels[exit].is_none() {
+                    labels[exit] = Some(label);
+                    open.push(exit);
+                }
+            }
+        }
+    }
+
+    let largest = (0..sizes.len()).max_by_key(|&label| sizes[label]);
+    let distance = |&idx: &usize| {
+        let (x, y) = map.idx_xy(idx);
+        (x as i32 - target.0).pow(2) + (y as i32 - target.1).pow(2)
+    };
+    let nearest = largest.and_then(|label| {
+        (0..labels.len())
+            .filter(|&idx| labels[idx] == Some(label))
+            .min_by_key(distance)
+    });
+    match nearest {
+        Some(idx) => {
+            let (x, y) = map.idx_xy(idx);
+            (x as i32, y as i32)
+        }
+        None => {
+            map.tiles[target.0 as usize][target.1 as usize] = TileType::Floor;
+            target
+        }
+    }
+}
+
+// Walls off any floor the player cannot walk to from `start`, then puts the
+// way down on the reachable tile furthest from it
+pub fn cull_unreachable_and_place_stairs(map: &mut Map, start: (i32, i32)) {
+    map.populate_blocked();
+    let start_idx = map.xy_idx(start.0, start.1);
+    let distances = DijkstraMap::new(
+        map.width,
+        map.height,
+        &[start_idx],
+        &*map,
+        map.dijkstra_max_depth(),
+    );
+
+    let mut furthest = (start, 0.0);
+    for x in 0..map.width {
+        for y in 0..map.height {
+            if map.tiles[x as usize][y as usize] != TileType::Floor {
+                continue;
+            }
+            let distance = distances.map[map.xy_idx(x, y)];
+            if distance == f32::MAX {
+                map.tiles[x as usize][y as usize] = TileType::Wall;
+            } else if distance > furthest.1 {
+                furthest = ((x, y), distance);
+            }
+        }
+    }
+
+    let ((stairs_x, stairs_y), _) = furthest;
+    map.tiles[stairs_x as usize][stairs_y as usize] = TileType::DownStairs;
+}
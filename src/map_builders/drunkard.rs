@@ -0,0 +1,75 @@
+use super::common::{chunk_regions, cull_unreachable_and_place_stairs, BuilderMap};
+use super::MapBuilder;
+use crate::map::TileType;
+use bracket_lib::prelude::*;
+
+// Share of the map to dig out, in percent
+const FLOOR_PERCENT: i32 = 50;
+// Steps a digger takes before it tires and another one sets off
+const DIGGER_LIFETIME: i32 = 400;
+
+// Diggers stagger about at random from somewhere already dug, until enough
+// of the map is open
+pub struct DrunkardsWalkBuilder {
+    build: BuilderMap,
+    starting_position: (i32, i32),
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new(depth: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder {
+            build: BuilderMap::new(depth),
+            starting_position: (0, 0),
+        }
+    }
+}
+
+impl MapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        let (width, height) = (self.build.map.width, self.build.map.height);
+        self.starting_position = (width / 2, height / 2);
+
+        let mut dug = vec![self.starting_position];
+        self.build.map.tiles[dug[0].0 as usize][dug[0].1 as usize] = TileType::Floor;
+
+        let wanted = (width - 2) * (height - 2) * FLOOR_PERCENT / 100;
+        while (dug.len() as i32) < wanted {
+            let (mut x, mut y) = dug[rng.range(0, dug.len() as i32) as usize];
+            for _ in 0..DIGGER_LIFETIME {
+                if self.build.map.tiles[x as usize][y as usize] == TileType::Wall {
+                    self.build.map.tiles[x as usize][y as usize] = TileType::Floor;
+                    dug.push((x, y));
+                }
+
+                // Diggers stay clear of the border so the map stays closed
+                match rng.roll_dice(1, 4) {
+                    1 if x > 2 => x -= 1,
+                    2 if x < width - 2 => x += 1,
+                    3 if y > 2 => y -= 1,
+                    4 if y < height - 2 => y += 1,
+                    _ => {}
+                }
+            }
+            self.build.take_snapshot();
+        }
+
+        cull_unreachable_and_place_stairs(&mut self.build.map, self.starting_position);
+        self.build.take_snapshot();
+    }
+
+    fn starting_position(&self) -> (i32, i32) {
+        self.starting_position
+    }
+
+    fn spawn_regions(&self) -> Vec<Vec<(i32, i32)>> {
+        chunk_regions(&self.build.map, self.starting_position)
+    }
+
+    fn door_positions(&self) -> Vec<(i32, i32)> {
+        Vec::new()
+    }
+
+    fn builder_map(&self) -> &BuilderMap {
+        &self.build
+    }
+}
@@ -0,0 +1,89 @@
+use super::common::{chunk_regions, cull_unreachable_and_place_stairs, BuilderMap};
+use super::MapBuilder;
+use crate::map::TileType;
+use bracket_lib::prelude::*;
+
+// Cells carved between snapshots; one per cell would be too slow to watch
+const CELLS_PER_SNAPSHOT: usize = 25;
+
+// A perfect maze, dug by a recursive backtracker. Cells sit on odd tiles and
+// the walls between them on even ones.
+pub struct MazeBuilder {
+    build: BuilderMap,
+}
+
+impl MazeBuilder {
+    pub fn new(depth: i32) -> MazeBuilder {
+        MazeBuilder {
+            build: BuilderMap::new(depth),
+        }
+    }
+
+    fn open(&mut self, x: i32, y: i32) {
+        self.build.map.tiles[x as usize][y as usize] = TileType::Floor;
+    }
+}
+
+impl MapBuilder for MazeBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        let cells_x = (self.build.map.width - 1) / 2;
+        let cells_y = (self.build.map.height - 1) / 2;
+        let mut visited = vec![vec![false; cells_y as usize]; cells_x as usize];
+
+        // Walk on from the newest cell while it has unvisited neighbours, and
+        // back up the path when it does not
+        let mut path = vec![(0, 0)];
+        let mut carved = 0;
+        visited[0][0] = true;
+        self.open(1, 1);
+        while let Some(&(cell_x, cell_y)) = path.last() {
+            let neighbours: Vec<(i32, i32)> = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .iter()
+                .map(|(delta_x, delta_y)| (cell_x + delta_x, cell_y + delta_y))
+                .filter(|&(x, y)| {
+                    x >= 0
+                        && x < cells_x
+                        && y >= 0
+                        && y < cells_y
+                        && !visited[x as usize][y as usize]
+                })
+                .collect();
+
+            if neighbours.is_empty() {
+                path.pop();
+                continue;
+            }
+
+            let (next_x, next_y) = neighbours[rng.range(0, neighbours.len() as i32) as usize];
+            visited[next_x as usize][next_y as usize] = true;
+            self.open(cell_x + next_x + 1, cell_y + next_y + 1);
+            self.open(next_x * 2 + 1, next_y * 2 + 1);
+            path.push((next_x, next_y));
+
+            carved += 1;
+            if carved % CELLS_PER_SNAPSHOT == 0 {
+                self.build.take_snapshot();
+            }
+        }
+
+        let start = self.starting_position();
+        cull_unreachable_and_place_stairs(&mut self.build.map, start);
+        self.build.take_snapshot();
+    }
+
+    fn starting_position(&self) -> (i32, i32) {
+        (1, 1)
+    }
+
+    fn spawn_regions(&self) -> Vec<Vec<(i32, i32)>> {
+        chunk_regions(&self.build.map, self.starting_position())
+    }
+
+    fn door_positions(&self) -> Vec<(i32, i32)> {
+        Vec::new()
+    }
+
+    fn builder_map(&self) -> &BuilderMap {
+        &self.build
+    }
+}
@@ -0,0 +1,94 @@
+use crate::map::Map;
+use bracket_lib::prelude::*;
+use std::str::FromStr;
+
+mod bsp;
+mod cellular_automata;
+mod common;
+mod drunkard;
+mod maze;
+mod simple_map;
+
+use bsp::BspBuilder;
+use cellular_automata::CellularAutomataBuilder;
+use common::BuilderMap;
+use drunkard::DrunkardsWalkBuilder;
+use maze::MazeBuilder;
+use simple_map::SimpleMapBuilder;
+
+// Everything a level needs from a generator: the map itself, where the
+// player arrives, the groups of floor tiles to fill with monsters and items
+// (the player's own surroundings are left out), and where doors fit between
+// rooms and corridors. The map and its snapshots along the way are kept in
+// a BuilderMap.
+pub trait MapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn starting_position(&self) -> (i32, i32);
+    fn spawn_regions(&self) -> Vec<Vec<(i32, i32)>>;
+    fn door_positions(&self) -> Vec<(i32, i32)>;
+    fn builder_map(&self) -> &BuilderMap;
+
+    fn map(&self) -> Map {
+        self.builder_map().map.clone()
+    }
+
+    fn snapshot_history(&self) -> Vec<Map> {
+        self.builder_map().history.clone()
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum BuilderKind {
+    Rooms,
+    Bsp,
+    Caves,
+    DrunkardsWalk,
+    Maze,
+}
+
+const ALL_KINDS: [BuilderKind; 5] = [
+    BuilderKind::Rooms,
+    BuilderKind::Bsp,
+    BuilderKind::Caves,
+    BuilderKind::DrunkardsWalk,
+    BuilderKind::Maze,
+];
+
+impl BuilderKind {
+    pub fn random(rng: &mut RandomNumberGenerator) -> BuilderKind {
+        ALL_KINDS[rng.range(0, ALL_KINDS.len() as i32) as usize]
+    }
+
+    // Name used on the command line and in replays
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuilderKind::Rooms => "rooms",
+            BuilderKind::Bsp => "bsp",
+            BuilderKind::Caves => "caves",
+            BuilderKind::DrunkardsWalk => "drunkard",
+            BuilderKind::Maze => "maze",
+        }
+    }
+
+    pub fn builder(&self, depth: i32) -> Box<dyn MapBuilder> {
+        match self {
+            BuilderKind::Rooms => Box::new(SimpleMapBuilder::new(depth)),
+            BuilderKind::Bsp => Box::new(BspBuilder::new(depth)),
+            BuilderKind::Caves => Box::new(CellularAutomataBuilder::new(depth)),
+            BuilderKind::DrunkardsWalk => Box::new(DrunkardsWalkBuilder::new(depth)),
+            BuilderKind::Maze => Box::new(MazeBuilder::new(depth)),
+        }
+    }
+}
+
+impl FromStr for BuilderKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<BuilderKind, String> {
+        ALL_KINDS
+            .iter()
+            .find(|kind| kind.name() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown map builder '{}'", name))
+    }
+}
@@ -0,0 +1,78 @@
+use super::common::{apply_room_to_map, connect, room_doors, room_regions, BuilderMap};
+use super::MapBuilder;
+use crate::map::TileType;
+use crate::rect::Rect;
+use bracket_lib::prelude::*;
+
+// Rooms dropped at random where they fit, each joined to the one before it
+pub struct SimpleMapBuilder {
+    build: BuilderMap,
+    rooms: Vec<Rect>,
+}
+
+impl SimpleMapBuilder {
+    pub fn new(depth: i32) -> SimpleMapBuilder {
+        SimpleMapBuilder {
+            build: BuilderMap::new(depth),
+            rooms: Vec::new(),
+        }
+    }
+}
+
+impl MapBuilder for SimpleMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        const MAX_ROOMS: i32 = 40;
+        const MIN_SIZE: i32 = 6;
+        const MAX_SIZE: i32 = 10;
+
+        for _ in 0..MAX_ROOMS {
+            let w = rng.range(MIN_SIZE, MAX_SIZE);
+            let h = rng.range(MIN_SIZE, MAX_SIZE);
+            let x = rng.roll_dice(1, self.build.map.width - w - 1) - 1;
+            let y = rng.roll_dice(1, self.build.map.height - h - 1) - 1;
+            let new_room = Rect::new(x, y, w, h);
+            if self
+                .rooms
+                .iter()
+                .all(|other_room| !new_room.intersect(other_room))
+                && !new_room.out_of_bounds(&self.build.map)
+            {
+                apply_room_to_map(&mut self.build.map, &new_room);
+                self.build.take_snapshot();
+
+                if let Some(prev_room) = self.rooms.last() {
+                    connect(
+                        &mut self.build.map,
+                        prev_room.center(),
+                        new_room.center(),
+                        rng,
+                    );
+                    self.build.take_snapshot();
+                }
+
+                self.rooms.push(new_room);
+            }
+        }
+
+        // The way down is always in the last room carved
+        let (stairs_x, stairs_y) = self.rooms[self.rooms.len() - 1].center();
+        self.build.map.tiles[stairs_x as usize][stairs_y as usize] = TileType::DownStairs;
+        self.build.take_snapshot();
+    }
+
+    fn starting_position(&self) -> (i32, i32) {
+        self.rooms[0].center()
+    }
+
+    fn spawn_regions(&self) -> Vec<Vec<(i32, i32)>> {
+        room_regions(&self.rooms)
+    }
+
+    fn door_positions(&self) -> Vec<(i32, i32)> {
+        room_doors(&self.build.map, &self.rooms)
+    }
+
+    fn builder_map(&self) -> &BuilderMap {
+        &self.build
+    }
+}
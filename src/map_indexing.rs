@@ -1,4 +1,4 @@
-use super::{BlocksTile, Map, Position};
+use super::{BlocksTile, BlocksVisibility, Map, Position};
 use specs::prelude::*;
 
 pub struct MapIndexingSystem {}
@@ -8,19 +8,26 @@ impl<'a> System<'a> for MapIndexingSystem {
         WriteExpect<'a, Map>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, BlocksTile>,
+        ReadStorage<'a, BlocksVisibility>,
         Entities<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, position, blockers, entities) = data;
+        let (mut map, position, blockers, view_blockers, entities) = data;
 
         map.populate_blocked();
         map.clear_content();
+        for line in map.view_blocked.iter_mut() {
+            line.fill(false);
+        }
         for (entity, pos) in (&entities, &position).join() {
             let p: Option<&BlocksTile> = blockers.get(entity);
             if p.is_some() {
                 map.blocked[pos.x as usize][pos.y as usize] = true;
             }
+            if view_blockers.contains(entity) {
+                map.view_blocked[pos.x as usize][pos.y as usize] = true;
+            }
             map.tile_content[pos.x as usize][pos.y as usize].push(entity);
         }
     }
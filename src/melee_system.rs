@@ -1,4 +1,6 @@
-use super::{CombatStats, GameLog, Name, SufferDamage, WantsToMelee};
+use super::{
+    CombatStats, DefenseBonus, Equipped, GameLog, MeleePowerBonus, Name, SufferDamage, WantsToMelee,
+};
 use specs::prelude::*;
 
 pub struct MeleeCombatSystem {}
@@ -11,13 +13,25 @@ impl<'a> System<'a> for MeleeCombatSystem {
         ReadStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
         WriteExpect<'a, GameLog>,
+        ReadStorage<'a, MeleePowerBonus>,
+        ReadStorage<'a, DefenseBonus>,
+        ReadStorage<'a, Equipped>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut wants_melee, names, combat_stats, mut inflict_damage, mut gamelog) =
-            data;
+        let (
+            entities,
+            mut wants_melee,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            mut gamelog,
+            melee_power_bonuses,
+            defense_bonuses,
+            equipped,
+        ) = data;
 
-        for (_entity, wants_melee, name, stats) in
+        for (entity, wants_melee, name, stats) in
             (&entities, &wants_melee, &names, &combat_stats).join()
         {
             if stats.hp > 0 {
@@ -25,7 +39,22 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 if target_stats.hp > 0 {
                     let target_name = names.get(wants_melee.target).unwrap();
 
-                    let damage = i32::max(0, stats.strength - target_stats.agility);
+                    let offensive_bonus: i32 = (&melee_power_bonuses, &equipped)
+                        .join()
+                        .filter(|(_bonus, equipped_by)| equipped_by.owner == entity)
+                        .map(|(bonus, _equipped_by)| bonus.power)
+                        .sum();
+                    let defensive_bonus: i32 = (&defense_bonuses, &equipped)
+                        .join()
+                        .filter(|(_bonus, equipped_by)| equipped_by.owner == wants_melee.target)
+                        .map(|(bonus, _equipped_by)| bonus.defense)
+                        .sum();
+
+                    let damage = i32::max(
+                        0,
+                        (stats.strength + offensive_bonus)
+                            - (target_stats.agility + defensive_bonus),
+                    );
 
                     if damage == 0 {
                         gamelog.entries.push(format!(
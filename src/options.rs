@@ -0,0 +1,83 @@
+use super::map_builders::BuilderKind;
+use std::str::FromStr;
+
+// Command line options. Unknown flags are rejected so typos are not
+// silently ignored.
+pub struct Options {
+    pub seed: Option<u64>,
+    // Script of inputs to play without opening a window
+    pub headless: Option<String>,
+    // Number of headless games to play, on consecutive seeds
+    pub games: u64,
+    // File to record the inputs of a new game into
+    pub record: Option<String>,
+    // Recorded game to play back, and how many frames to advance per frame
+    pub replay: Option<String>,
+    pub replay_speed: u32,
+    // Spawn definitions to use instead of the built-in ones
+    pub raws: Option<String>,
+    // Print the spawn chances down to this depth instead of playing
+    pub spawn_report: Option<i32>,
+    // Generate every level with this builder instead of a random one each
+    pub map_builder: Option<BuilderKind>,
+    // Show each level being generated before it is played
+    pub visualize_mapgen: bool,
+    // Time the monsters' pathing with this many of them instead of playing
+    pub bench_pathing: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            seed: None,
+            headless: None,
+            games: 1,
+            record: None,
+            replay: None,
+            replay_speed: 1,
+            raws: None,
+            spawn_report: None,
+            map_builder: None,
+            visualize_mapgen: false,
+            bench_pathing: None,
+        }
+    }
+}
+
+impl Options {
+    pub fn from_args() -> Result<Options, String> {
+        Options::parse(std::env::args().skip(1))
+    }
+
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => options.seed = Some(parse_value(&arg, args.next())?),
+                "--headless" => options.headless = Some(parse_value(&arg, args.next())?),
+                "--games" => options.games = parse_value(&arg, args.next())?,
+                "--record" => options.record = Some(parse_value(&arg, args.next())?),
+                "--replay" => options.replay = Some(parse_value(&arg, args.next())?),
+                "--replay-speed" => options.replay_speed = parse_value(&arg, args.next())?,
+                "--raws" => options.raws = Some(parse_value(&arg, args.next())?),
+                "--spawn-report" => options.spawn_report = Some(parse_value(&arg, args.next())?),
+                "--map-builder" => options.map_builder = Some(parse_value(&arg, args.next())?),
+                "--visualize-mapgen" => options.visualize_mapgen = true,
+                "--bench-pathing" => options.bench_pathing = Some(parse_value(&arg, args.next())?),
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    match value {
+        None => Err(format!("missing value for '{}'", flag)),
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("invalid value '{}' for '{}'", value, flag)),
+    }
+}
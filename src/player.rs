@@ -2,11 +2,38 @@ use bracket_lib::prelude::*;
 use specs::prelude::*;
 use std::cmp::{max, min};
 
+use super::camera::Camera;
+use super::status_system::{has_status, stumble_direction};
+use super::trap_system::perception_roll;
 use super::{
-    CombatStats, GameLog, Item, Map, Player, Position, RunState, State, Viewshed,
-    WantsToDisplayContent, WantsToMelee, WantsToPickupItem,
+    Ammunition, CombatStats, Door, EntityMoved, EntryTrigger, Equipped, GameLog, Hidden,
+    HungerClock, HungerState, InBackpack, Input, Item, Map, Monster, Name, Player, Position,
+    RangedWeapon, RunState, State, StatusKind, TileType, Viewshed, WantsToDisplayContent,
+    WantsToMelee, WantsToOpenDoor, WantsToPickupItem,
 };
-use super::{MAP_X, MAP_Y};
+use super::{REST_HEAL_AMOUNT, SEARCH_BONUS, SEARCH_RADIUS};
+
+// Commands that play out over many turns, until they are done or something
+// interrupts them. All remember the player's health after the last turn, to
+// notice anything that hurts them.
+pub enum Activity {
+    Resting {
+        hp: i32,
+    },
+    // Items and stairs on tiles still to be revealed, to stop at the sight
+    // of them
+    Exploring {
+        hp: i32,
+        unseen: Vec<(Point, String)>,
+    },
+    // The tiles still to walk through, by index, the next one first, and
+    // the monsters already in sight, which do not stop the journey
+    Travelling {
+        hp: i32,
+        path: Vec<usize>,
+        in_view: Vec<Entity>,
+    },
+}
 
 fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let runstate = ecs.fetch::<RunState>();
@@ -14,14 +41,25 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
         return;
     }
 
+    let player_entity = *ecs.fetch::<Entity>();
+    let (delta_x, delta_y) = if has_status(ecs, player_entity, StatusKind::Confusion) {
+        stumble_direction(&mut ecs.write_resource::<RandomNumberGenerator>())
+    } else {
+        (delta_x, delta_y)
+    };
+    let held = has_status(ecs, player_entity, StatusKind::Held);
+
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
     let mut viewshed = ecs.write_storage::<Viewshed>();
     let combat_stats = ecs.read_storage::<CombatStats>();
     let items = ecs.read_storage::<Item>();
+    let doors = ecs.read_storage::<Door>();
     let entities = ecs.entities();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let mut wants_to_open = ecs.write_storage::<WantsToOpenDoor>();
     let mut wants_to_display = ecs.write_storage::<WantsToDisplayContent>();
+    let mut entity_moved = ecs.write_storage::<EntityMoved>();
     let map = ecs.fetch::<Map>();
 
     for (entity, _player, pos, viewshed) in
@@ -29,6 +67,9 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     {
         let destination_x = pos.x + delta_x;
         let destination_y = pos.y + delta_y;
+        if map.squeezes_between_walls(pos.x, pos.y, delta_x, delta_y) {
+            return;
+        }
 
         for potential_target in
             map.tile_content[destination_x as usize][destination_y as usize].iter()
@@ -46,6 +87,19 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                 return;
             }
 
+            // Walking into a closed door opens it, or tries the lock
+            if matches!(doors.get(*potential_target), Some(door) if !door.open) {
+                wants_to_open
+                    .insert(
+                        entity,
+                        WantsToOpenDoor {
+                            door: *potential_target,
+                        },
+                    )
+                    .expect("Add target failed");
+                return;
+            }
+
             let item = items.get(*potential_target);
             if item.is_some() {
                 wants_to_display
@@ -54,19 +108,366 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             }
         }
 
+        // Held fast, the player can still fight but cannot step away
+        if held {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog
+                .entries
+                .push("You struggle, but cannot move.".to_string());
+            return;
+        }
+
         if !map.blocked[destination_x as usize][destination_y as usize] {
-            pos.x = min(MAP_X - 1, max(0, destination_x));
-            pos.y = min(MAP_Y - 1, max(0, destination_y));
+            pos.x = min(map.width - 1, max(0, destination_x));
+            pos.y = min(map.height - 1, max(0, destination_y));
 
             let mut ppos = ecs.write_resource::<Point>();
             ppos.x = pos.x;
             ppos.y = pos.y;
 
             viewshed.dirty = true;
+            entity_moved
+                .insert(entity, EntityMoved {})
+                .expect("Unable to insert marker");
+        }
+    }
+}
+
+// A careful look around for hidden things, with a much better chance of
+// spotting them than just walking past
+fn search(ecs: &mut World) {
+    let player_pos = *ecs.fetch::<Point>();
+    let player_entity = *ecs.fetch::<Entity>();
+    let map = ecs.fetch::<Map>();
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let names = ecs.read_storage::<Name>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let mut hidden = ecs.write_storage::<Hidden>();
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    gamelog.entries.push("You search the area.".to_string());
+    let stats = match combat_stats.get(player_entity) {
+        Some(stats) => stats,
+        None => return,
+    };
+
+    let mut found: Vec<Entity> = Vec::new();
+    for (entity, pos, _hidden) in (&entities, &positions, &hidden).join() {
+        let distance = DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), player_pos);
+        if distance <= SEARCH_RADIUS
+            && map.visible_tiles[pos.x as usize][pos.y as usize]
+            && perception_roll(&mut rng, stats, SEARCH_BONUS)
+        {
+            found.push(entity);
+        }
+    }
+
+    for entity in found {
+        hidden.remove(entity);
+        if let Some(name) = names.get(entity) {
+            gamelog.entries.push(format!("You find a {}.", name.name));
         }
     }
 }
 
+// Lets a turn go by. Out of sight of any monster, and with nothing to eat at
+// them, it gives the player a moment to recover.
+fn wait(ecs: &mut World) {
+    if !monsters_in_view(ecs).is_empty() || hungry(ecs) {
+        return;
+    }
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut combat_stats = ecs.write_storage::<CombatStats>();
+    if let Some(stats) = combat_stats.get_mut(player_entity) {
+        stats.hp = i32::min(stats.max_hp, stats.hp + REST_HEAL_AMOUNT);
+    }
+}
+
+fn hungry(ecs: &World) -> bool {
+    let player_entity = *ecs.fetch::<Entity>();
+    let hunger_clocks = ecs.read_storage::<HungerClock>();
+    matches!(
+        hunger_clocks.get(player_entity),
+        Some(clock) if clock.state == HungerState::Hungry || clock.state == HungerState::Starving
+    )
+}
+
+// The monsters the player can see, with their names
+fn monsters_in_view(ecs: &World) -> Vec<(Entity, String)> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let entities = ecs.entities();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    let names = ecs.read_storage::<Name>();
+
+    let viewshed = match viewsheds.get(player_entity) {
+        Some(viewshed) => viewshed,
+        None => return Vec::new(),
+    };
+    (&entities, &monsters, &positions, &names)
+        .join()
+        .filter(|(_entity, _monster, pos, _name)| {
+            viewshed.visible_tiles.contains(&Point::new(pos.x, pos.y))
+        })
+        .map(|(entity, _monster, _pos, name)| (entity, name.name.clone()))
+        .collect()
+}
+
+// Items lying around and the way down, on tiles the player has not seen yet
+fn unseen_sights(ecs: &World) -> Vec<(Point, String)> {
+    let map = ecs.fetch::<Map>();
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+    let names = ecs.read_storage::<Name>();
+
+    let mut sights: Vec<(Point, String)> = (&items, &positions, &names)
+        .join()
+        .filter(|(_item, pos, _name)| !map.revealed_tiles[pos.x as usize][pos.y as usize])
+        .map(|(_item, pos, name)| (Point::new(pos.x, pos.y), name.name.clone()))
+        .collect();
+    for x in 0..map.width {
+        for y in 0..map.height {
+            if !map.revealed_tiles[x as usize][y as usize]
+                && map.tiles[x as usize][y as usize] == TileType::DownStairs
+            {
+                sights.push((Point::new(x, y), "way down".to_string()));
+            }
+        }
+    }
+    sights
+}
+
+// Runs `plan` on the map with the traps the player knows about blocked off,
+// so that the ways it finds keep clear of them
+fn avoiding_known_traps<T>(ecs: &World, plan: impl FnOnce(&Map) -> T) -> T {
+    let mut map = ecs.fetch_mut::<Map>();
+    let positions = ecs.read_storage::<Position>();
+    let entry_triggers = ecs.read_storage::<EntryTrigger>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    let traps: Vec<(usize, usize, bool)> = (&positions, &entry_triggers, !&hidden)
+        .join()
+        .map(|(pos, _trigger, _hidden)| {
+            let (x, y) = (pos.x as usize, pos.y as usize);
+            (x, y, map.blocked[x][y])
+        })
+        .collect();
+    for (x, y, _was_blocked) in traps.iter() {
+        map.blocked[*x][*y] = true;
+    }
+
+    let result = plan(&map);
+
+    for (x, y, was_blocked) in traps {
+        map.blocked[x][y] = was_blocked;
+    }
+    result
+}
+
+// The next step toward the closest floor the player has not seen yet
+fn explore_step(ecs: &World) -> Option<(i32, i32)> {
+    let player_pos = *ecs.fetch::<Point>();
+    avoiding_known_traps(ecs, |map| {
+        let mut unexplored = Vec::new();
+        for x in 0..map.width {
+            for y in 0..map.height {
+                if !map.revealed_tiles[x as usize][y as usize]
+                    && map.tiles[x as usize][y as usize] != TileType::Wall
+                {
+                    unexplored.push(map.xy_idx(x, y));
+                }
+            }
+        }
+        let distances = DijkstraMap::new(
+            map.width,
+            map.height,
+            &unexplored,
+            map,
+            map.dijkstra_max_depth(),
+        );
+        let here = map.xy_idx(player_pos.x, player_pos.y);
+        DijkstraMap::find_lowest_exit(&distances, here, map)
+            .filter(|next| distances.map[*next] < distances.map[here])
+            .map(|next| {
+                let (x, y) = map.idx_xy(next);
+                (x as i32 - player_pos.x, y as i32 - player_pos.y)
+            })
+    })
+}
+
+fn player_health(ecs: &World) -> (i32, i32) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    match combat_stats.get(player_entity) {
+        Some(stats) => (stats.hp, stats.max_hp),
+        None => (0, 0),
+    }
+}
+
+fn interrupt(ecs: &mut World, message: String) -> RunState {
+    ecs.fetch_mut::<GameLog>().entries.push(message);
+    RunState::AwaitingInput
+}
+
+// Sets off toward a revealed tile clicked on the map, along the shortest way
+// there that keeps clear of known traps
+fn travel_to(gs: &mut State, x: i32, y: i32) -> RunState {
+    let player_pos = *gs.ecs.fetch::<Point>();
+    {
+        let map = gs.ecs.fetch::<Map>();
+        if x < 0
+            || x >= map.width
+            || y < 0
+            || y >= map.height
+            || !map.revealed_tiles[x as usize][y as usize]
+            || (x, y) == (player_pos.x, player_pos.y)
+        {
+            return RunState::AwaitingInput;
+        }
+    }
+    let path = avoiding_known_traps(&gs.ecs, |map| {
+        a_star_search(
+            map.xy_idx(player_pos.x, player_pos.y),
+            map.xy_idx(x, y),
+            map,
+        )
+    });
+    if !path.success {
+        let message = "There is no way there.".to_string();
+        return interrupt(&mut gs.ecs, message);
+    }
+
+    let (hp, _max_hp) = player_health(&gs.ecs);
+    let in_view = monsters_in_view(&gs.ecs)
+        .into_iter()
+        .map(|(monster, _name)| monster)
+        .collect();
+    gs.activity = Some(Activity::Travelling {
+        hp,
+        path: path.steps[1..].to_vec(),
+        in_view,
+    });
+    RunState::Continuing
+}
+
+// Takes the next turn of resting, exploring or travelling, or hands control
+// back to the player once it is done or something needs their attention
+pub fn continue_activity(gs: &mut State) -> RunState {
+    let activity = match gs.activity.take() {
+        Some(activity) => activity,
+        None => return RunState::AwaitingInput,
+    };
+
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    if has_status(&gs.ecs, player_entity, StatusKind::Sleep) {
+        gs.activity = Some(activity);
+        return RunState::PlayerTurn;
+    }
+    // Travel only stops for monsters that come into sight on the way, not
+    // for those the player could already see when setting off
+    let in_view = monsters_in_view(&gs.ecs);
+    let spotted = match &activity {
+        Activity::Travelling { in_view: seen, .. } => in_view
+            .iter()
+            .find(|(monster, _name)| !seen.contains(monster)),
+        _ => in_view.first(),
+    };
+    if let Some((_monster, name)) = spotted {
+        let message = format!("You spot a {}.", name);
+        return interrupt(&mut gs.ecs, message);
+    }
+
+    let (hp_now, max_hp) = player_health(&gs.ecs);
+    match activity {
+        Activity::Resting { hp } => {
+            if hp_now < hp {
+                return interrupt(&mut gs.ecs, "You stop resting.".to_string());
+            }
+            if hungry(&gs.ecs) {
+                return interrupt(&mut gs.ecs, "You are too hungry to rest.".to_string());
+            }
+            if hp_now >= max_hp {
+                return interrupt(&mut gs.ecs, "You are at full health.".to_string());
+            }
+
+            wait(&mut gs.ecs);
+            let (hp, _max_hp) = player_health(&gs.ecs);
+            gs.activity = Some(Activity::Resting { hp });
+        }
+        Activity::Exploring { hp, unseen } => {
+            if hp_now < hp {
+                return interrupt(&mut gs.ecs, "You stop exploring.".to_string());
+            }
+            let spotted = {
+                let map = gs.ecs.fetch::<Map>();
+                unseen
+                    .iter()
+                    .find(|(pos, _name)| map.revealed_tiles[pos.x as usize][pos.y as usize])
+                    .map(|(_pos, name)| name.clone())
+            };
+            if let Some(name) = spotted {
+                return interrupt(&mut gs.ecs, format!("You spot the {}.", name));
+            }
+            if has_status(&gs.ecs, player_entity, StatusKind::Held)
+                || has_status(&gs.ecs, player_entity, StatusKind::Confusion)
+            {
+                return interrupt(&mut gs.ecs, "You are in no state to explore.".to_string());
+            }
+            let (delta_x, delta_y) = match explore_step(&gs.ecs) {
+                Some(step) => step,
+                None => {
+                    let message = "There is nothing left to explore.".to_string();
+                    return interrupt(&mut gs.ecs, message);
+                }
+            };
+
+            let unseen = unseen_sights(&gs.ecs);
+            try_move_player(delta_x, delta_y, &mut gs.ecs);
+            gs.activity = Some(Activity::Exploring { hp: hp_now, unseen });
+        }
+        Activity::Travelling { hp, mut path, .. } => {
+            if hp_now < hp {
+                return interrupt(&mut gs.ecs, "You stop travelling.".to_string());
+            }
+            if has_status(&gs.ecs, player_entity, StatusKind::Held)
+                || has_status(&gs.ecs, player_entity, StatusKind::Confusion)
+            {
+                return interrupt(&mut gs.ecs, "You are in no state to travel.".to_string());
+            }
+            let (next_x, next_y, blocked) = {
+                let map = gs.ecs.fetch::<Map>();
+                let (x, y) = map.idx_xy(path[0]);
+                (x as i32, y as i32, map.blocked[x][y])
+            };
+            if blocked {
+                return interrupt(&mut gs.ecs, "Something is in the way.".to_string());
+            }
+
+            // A closed door on the way takes a turn to open, and the step
+            // onto it is taken after
+            let player_pos = *gs.ecs.fetch::<Point>();
+            try_move_player(next_x - player_pos.x, next_y - player_pos.y, &mut gs.ecs);
+            if *gs.ecs.fetch::<Point>() == Point::new(next_x, next_y) {
+                path.remove(0);
+            }
+            if !path.is_empty() {
+                gs.activity = Some(Activity::Travelling {
+                    hp: hp_now,
+                    path,
+                    in_view: in_view
+                        .into_iter()
+                        .map(|(monster, _name)| monster)
+                        .collect(),
+                });
+            }
+        }
+    }
+    RunState::PlayerTurn
+}
+
 fn get_item(ecs: &mut World) {
     let player_pos = ecs.fetch::<Point>();
     let player_entity = ecs.fetch::<Entity>();
@@ -101,8 +502,74 @@ fn get_item(ecs: &mut World) {
     }
 }
 
-pub fn player_input(gs: &mut State, ctx: &mut BTerm) -> RunState {
-    match ctx.key {
+// Picks a target for the equipped ranged weapon, if there is ammunition to
+// fire from it
+fn fire(ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let ranged_weapons = ecs.read_storage::<RangedWeapon>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let ammunition = ecs.read_storage::<Ammunition>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let weapon = (&entities, &ranged_weapons, &equipped)
+        .join()
+        .find(|(_item, _weapon, equipped_by)| equipped_by.owner == player_entity);
+    let (item, weapon) = match weapon {
+        Some((item, weapon, _equipped_by)) => (item, weapon),
+        None => {
+            gamelog
+                .entries
+                .push("You have nothing to fire with.".to_string());
+            return RunState::AwaitingInput;
+        }
+    };
+
+    let has_ammo = (&backpack, &ammunition)
+        .join()
+        .any(|(pack, _ammo)| pack.owner == player_entity);
+    if !has_ammo {
+        gamelog.entries.push("You have no ammunition.".to_string());
+        return RunState::AwaitingInput;
+    }
+
+    RunState::ShowTargeting {
+        range: weapon.range,
+        item,
+    }
+}
+
+fn try_next_level(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    if map.tiles[player_pos.x as usize][player_pos.y as usize] == TileType::DownStairs {
+        true
+    } else {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("There is no way down from here.".to_string());
+        false
+    }
+}
+
+pub fn player_input(gs: &mut State, input: &Input) -> RunState {
+    // A sleeping player loses their turn whatever they press
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    if has_status(&gs.ecs, player_entity, StatusKind::Sleep) {
+        return RunState::PlayerTurn;
+    }
+
+    // Clicks land on the screen, and the camera says where that is on the map
+    if input.left_click {
+        return match Camera::of_player(&gs.ecs).to_world(input.mouse_pos) {
+            Some(target) => travel_to(gs, target.x, target.y),
+            None => RunState::AwaitingInput,
+        };
+    }
+
+    match input.key {
         None => return RunState::AwaitingInput,
         Some(key) => match key {
             VirtualKeyCode::Numpad4 | VirtualKeyCode::H | VirtualKeyCode::Left => {
@@ -117,10 +584,37 @@ pub fn player_input(gs: &mut State, ctx: &mut BTerm) -> RunState {
             VirtualKeyCode::Numpad2 | VirtualKeyCode::J | VirtualKeyCode::Down => {
                 try_move_player(0, 1, &mut gs.ecs)
             }
+            VirtualKeyCode::Numpad7 | VirtualKeyCode::Y => try_move_player(-1, -1, &mut gs.ecs),
+            VirtualKeyCode::Numpad9 | VirtualKeyCode::U => try_move_player(1, -1, &mut gs.ecs),
+            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => try_move_player(-1, 1, &mut gs.ecs),
+            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => try_move_player(1, 1, &mut gs.ecs),
+            VirtualKeyCode::Numpad5 | VirtualKeyCode::Space => wait(&mut gs.ecs),
+            VirtualKeyCode::R => {
+                let (hp, _max_hp) = player_health(&gs.ecs);
+                gs.activity = Some(Activity::Resting { hp });
+                return RunState::Continuing;
+            }
+            VirtualKeyCode::X => {
+                let (hp, _max_hp) = player_health(&gs.ecs);
+                gs.activity = Some(Activity::Exploring {
+                    hp,
+                    unseen: unseen_sights(&gs.ecs),
+                });
+                return RunState::Continuing;
+            }
             VirtualKeyCode::G => get_item(&mut gs.ecs),
+            VirtualKeyCode::S => search(&mut gs.ecs),
+            VirtualKeyCode::F => return fire(&mut gs.ecs),
+            VirtualKeyCode::Period => {
+                if try_next_level(&mut gs.ecs) {
+                    return RunState::NextLevel;
+                }
+                return RunState::AwaitingInput;
+            }
             VirtualKeyCode::I => return RunState::ShowInventory,
             VirtualKeyCode::D => return RunState::ShowDropItem,
             VirtualKeyCode::C => return RunState::ShowCharacter,
+            VirtualKeyCode::Escape => return RunState::SaveGame,
             _ => return RunState::AwaitingInput,
         },
     }
@@ -0,0 +1,117 @@
+use bracket_lib::prelude::*;
+use serde::Deserialize;
+
+// Each tier is half as likely as the one before it, on top of the entry's
+// own weight
+#[derive(Deserialize, PartialEq, Copy, Clone, Debug, Default)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    fn multiplier(&self) -> i32 {
+        match self {
+            Rarity::Common => 8,
+            Rarity::Uncommon => 4,
+            Rarity::Rare => 2,
+            Rarity::Legendary => 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TableEntry {
+    pub name: String,
+    pub weight: i32,
+    pub min_depth: i32,
+    pub max_depth: Option<i32>,
+    pub rarity: Rarity,
+}
+
+impl TableEntry {
+    pub fn new<S: ToString>(name: S, weight: i32) -> TableEntry {
+        TableEntry {
+            name: name.to_string(),
+            weight,
+            min_depth: 1,
+            max_depth: None,
+            rarity: Rarity::Common,
+        }
+    }
+
+    // Chance of this entry relative to the others, zero outside its depths
+    fn weight_at(&self, depth: i32) -> i32 {
+        let too_deep = match self.max_depth {
+            Some(max_depth) => depth > max_depth,
+            None => false,
+        };
+        if depth < self.min_depth || too_deep {
+            0
+        } else {
+            self.weight * self.rarity.multiplier()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RandomTable {
+    entries: Vec<TableEntry>,
+}
+
+impl RandomTable {
+    pub fn new() -> RandomTable {
+        RandomTable {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add(mut self, entry: TableEntry) -> RandomTable {
+        self.entries.push(entry);
+        self
+    }
+
+    // Name of a random entry available at this depth, each as likely as its
+    // weight; None when nothing can appear there
+    pub fn roll(&self, rng: &mut RandomNumberGenerator, depth: i32) -> Option<&str> {
+        let total: i32 = self.entries.iter().map(|e| e.weight_at(depth)).sum();
+        if total <= 0 {
+            return None;
+        }
+
+        let mut roll = rng.roll_dice(1, total) - 1;
+        for entry in self.entries.iter() {
+            let weight = entry.weight_at(depth);
+            if roll < weight {
+                return Some(&entry.name);
+            }
+            roll -= weight;
+        }
+        None
+    }
+
+    // Chance of each entry that can appear at this depth, most likely first
+    pub fn distribution(&self, depth: i32) -> Vec<(&str, f32)> {
+        let total: i32 = self.entries.iter().map(|e| e.weight_at(depth)).sum();
+        if total <= 0 {
+            return Vec::new();
+        }
+
+        let mut chances: Vec<(&str, f32)> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.weight_at(depth) > 0)
+            .map(|entry| {
+                (
+                    entry.name.as_str(),
+                    entry.weight_at(depth) as f32 / total as f32,
+                )
+            })
+            .collect();
+        chances.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        chances
+    }
+}
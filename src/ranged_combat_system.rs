@@ -0,0 +1,209 @@
+use super::{
+    Ammunition, CombatStats, DefenseBonus, Equipped, GameLog, InBackpack, Item, Map, Name,
+    Position, RangedWeapon, Renderable, SerializeMe, SufferDamage, WantsToShoot, RANGED_ACCURACY,
+    RANGED_ACCURACY_FALLOFF, RANGED_MIN_ACCURACY,
+};
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+
+// Where a shot from `from` towards `to` comes down: on the first tile in the
+// way that something stands on, or on the last open tile before a wall or a
+// closed door
+pub fn line_of_fire(map: &Map, from: Point, to: Point) -> Point {
+    let mut landing = from;
+    for point in line2d(LineAlg::Bresenham, from, to).into_iter().skip(1) {
+        if point.x < 0 || point.x >= map.width || point.y < 0 || point.y >= map.height {
+            break;
+        }
+        if map.is_opaque(map.xy_idx(point.x, point.y)) {
+            break;
+        }
+        landing = point;
+        if map.blocked[point.x as usize][point.y as usize] {
+            break;
+        }
+    }
+    landing
+}
+
+// The weapon `shooter` fires with: its own, or the one it has equipped
+pub fn ranged_weapon_of(
+    shooter: Entity,
+    ranged_weapons: &ReadStorage<RangedWeapon>,
+    equipped: &ReadStorage<Equipped>,
+) -> Option<RangedWeapon> {
+    if let Some(weapon) = ranged_weapons.get(shooter) {
+        return Some(*weapon);
+    }
+    (ranged_weapons, equipped)
+        .join()
+        .find(|(_weapon, equipped_by)| equipped_by.owner == shooter)
+        .map(|(weapon, _equipped_by)| *weapon)
+}
+
+fn hit_chance(distance: f32) -> i32 {
+    let falloff = RANGED_ACCURACY_FALLOFF * (distance.round() as i32 - 1);
+    i32::max(RANGED_MIN_ACCURACY, RANGED_ACCURACY - falloff)
+}
+
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        Read<'a, LazyUpdate>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, RangedWeapon>,
+        ReadStorage<'a, DefenseBonus>,
+        ReadStorage<'a, Equipped>,
+        WriteStorage<'a, Ammunition>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Renderable>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            map,
+            mut gamelog,
+            mut rng,
+            lazy,
+            mut wants_shoot,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            ranged_weapons,
+            defense_bonuses,
+            equipped,
+            mut ammunition,
+            backpack,
+            positions,
+            renderables,
+        ) = data;
+
+        for (entity, wants_shoot, name, stats, pos) in
+            (&entities, &wants_shoot, &names, &combat_stats, &positions).join()
+        {
+            if stats.hp <= 0 {
+                continue;
+            }
+            let weapon = match ranged_weapon_of(entity, &ranged_weapons, &equipped) {
+                Some(weapon) => weapon,
+                None => continue,
+            };
+
+            // A wielded bow needs ammunition from the backpack, which a
+            // monster shooting on its own does not
+            let mut spent_ammo: Option<(Name, Renderable)> = None;
+            if !ranged_weapons.contains(entity) {
+                let stack = (&entities, &backpack, &mut ammunition)
+                    .join()
+                    .find(|(_item, pack, _ammo)| pack.owner == entity);
+                let (stack, _pack, ammo) = match stack {
+                    Some(stack) => stack,
+                    None => {
+                        gamelog
+                            .entries
+                            .push(format!("{} has no ammunition.", &name.name));
+                        continue;
+                    }
+                };
+                if let (Some(name), Some(render)) = (names.get(stack), renderables.get(stack)) {
+                    spent_ammo = Some((name.clone(), render.clone()));
+                }
+                ammo.count -= 1;
+                if ammo.count <= 0 {
+                    entities.delete(stack).expect("Delete failed");
+                }
+            }
+
+            let from = Point::new(pos.x, pos.y);
+            let landing = line_of_fire(&map, from, wants_shoot.target);
+            let target = map.tile_content[landing.x as usize][landing.y as usize]
+                .iter()
+                .find(|mob| **mob != entity && combat_stats.contains(**mob))
+                .copied();
+
+            let distance = DistanceAlg::Pythagoras.distance2d(from, landing);
+            let hit = target.is_some() && rng.roll_dice(1, 100) <= hit_chance(distance);
+            match target {
+                Some(target) if hit => {
+                    let target_stats = combat_stats.get(target).unwrap();
+                    let target_name = names.get(target).unwrap();
+                    let defensive_bonus: i32 = (&defense_bonuses, &equipped)
+                        .join()
+                        .filter(|(_bonus, equipped_by)| equipped_by.owner == target)
+                        .map(|(bonus, _equipped_by)| bonus.defense)
+                        .sum();
+
+                    let damage = i32::max(
+                        0,
+                        (stats.agility + weapon.power) - (target_stats.agility + defensive_bonus),
+                    );
+                    if damage == 0 {
+                        gamelog.entries.push(format!(
+                            "{} shoots {}, but is unable to hurt it.",
+                            &name.name, &target_name.name
+                        ));
+                    } else {
+                        gamelog.entries.push(format!(
+                            "{} shoots {}, for {} hp.",
+                            &name.name, &target_name.name, damage
+                        ));
+                        SufferDamage::new_damage(&mut inflict_damage, target, damage);
+                    }
+                    continue;
+                }
+                Some(target) => {
+                    gamelog.entries.push(format!(
+                        "{} shoots at {}, and misses.",
+                        &name.name,
+                        &names.get(target).unwrap().name
+                    ));
+                }
+                None => {
+                    gamelog
+                        .entries
+                        .push(format!("{} shoots, and hits nothing.", &name.name));
+                }
+            }
+
+            // A shot that misses falls to the floor, to be picked up again,
+            // joining a pile of the same kind already lying there
+            if let Some((ammo_name, ammo_render)) = spent_ammo {
+                let pile = map.tile_content[landing.x as usize][landing.y as usize]
+                    .iter()
+                    .find(|item| {
+                        ammunition.contains(**item)
+                            && matches!(names.get(**item), Some(name) if name.name == ammo_name.name)
+                    })
+                    .copied();
+                if let Some(ammo) = pile.and_then(|pile| ammunition.get_mut(pile)) {
+                    ammo.count += 1;
+                } else {
+                    lazy.create_entity(&entities)
+                        .with(Position {
+                            x: landing.x,
+                            y: landing.y,
+                        })
+                        .with(ammo_render)
+                        .with(ammo_name)
+                        .with(Item {})
+                        .with(Ammunition { count: 1 })
+                        .marked::<SimpleMarker<SerializeMe>>()
+                        .build();
+                }
+            }
+        }
+
+        wants_shoot.clear();
+    }
+}
@@ -0,0 +1,429 @@
+use super::random_table::{RandomTable, Rarity, TableEntry};
+use super::{BehaviourKind, EquipmentSlot, StatusKind};
+use serde::Deserialize;
+use std::fs;
+
+// The shipped definitions are built into the binary so the game runs from
+// anywhere, including the browser. `--raws` loads an edited copy instead.
+const EMBEDDED_SPAWNS: &str = include_str!("../raws/spawns.json");
+
+fn one() -> i32 {
+    1
+}
+
+// A number that is either fixed, or grows by `gain` every `every` levels
+// below the first, e.g. { "base": 8, "gain": 2 } is 8, 10, 12...
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(untagged)]
+pub enum Scaled {
+    Flat(i32),
+    ByDepth {
+        base: i32,
+        gain: i32,
+        #[serde(default = "one")]
+        every: i32,
+    },
+}
+
+impl Scaled {
+    pub fn at(&self, depth: i32) -> i32 {
+        match *self {
+            Scaled::Flat(value) => value,
+            Scaled::ByDepth { base, gain, every } => base + gain * ((depth - 1) / every),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        match *self {
+            Scaled::Flat(_) => true,
+            Scaled::ByDepth { every, .. } => every > 0,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnDef {
+    pub weight: i32,
+    #[serde(default = "one")]
+    pub min_depth: i32,
+    #[serde(default)]
+    pub max_depth: Option<i32>,
+    #[serde(default)]
+    pub rarity: Rarity,
+}
+
+impl SpawnDef {
+    fn table_entry(&self, name: &str) -> TableEntry {
+        TableEntry {
+            min_depth: self.min_depth,
+            max_depth: self.max_depth,
+            rarity: self.rarity,
+            ..TableEntry::new(name, self.weight)
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MonsterStatsDef {
+    pub level: Scaled,
+    pub vitality: Scaled,
+    pub strength: Scaled,
+    pub agility: Scaled,
+    #[serde(default = "zero")]
+    pub magic: Scaled,
+}
+
+fn zero() -> Scaled {
+    Scaled::Flat(0)
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RangedWeaponDef {
+    pub range: i32,
+    pub power: i32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MonsterDef {
+    pub name: String,
+    pub glyph: String,
+    pub color: (u8, u8, u8),
+    pub vision: i32,
+    pub stats: MonsterStatsDef,
+    #[serde(default)]
+    pub behaviour: BehaviourKind,
+    // Monsters shoot without needing ammunition
+    #[serde(default)]
+    pub ranged: Option<RangedWeaponDef>,
+    pub spawn: SpawnDef,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StatusDef {
+    pub kind: StatusKind,
+    pub turns: i32,
+    #[serde(default = "zero")]
+    pub potency: Scaled,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EquipDef {
+    pub slot: EquipmentSlot,
+    #[serde(default)]
+    pub power: i32,
+    #[serde(default)]
+    pub defense: i32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ItemDef {
+    pub name: String,
+    pub glyph: String,
+    pub color: (u8, u8, u8),
+    #[serde(default)]
+    pub consumable: bool,
+    #[serde(default)]
+    pub heal: Option<Scaled>,
+    #[serde(default)]
+    pub damage: Option<Scaled>,
+    #[serde(default)]
+    pub range: Option<i32>,
+    #[serde(default)]
+    pub area_of_effect: Option<i32>,
+    #[serde(default)]
+    pub status: Option<StatusDef>,
+    #[serde(default)]
+    pub food: bool,
+    #[serde(default)]
+    pub equippable: Option<EquipDef>,
+    // Opens one locked door. Every level with locked doors gets enough keys,
+    // so keys usually have a spawn weight of 0.
+    #[serde(default)]
+    pub key: bool,
+    // Reveals the whole level
+    #[serde(default)]
+    pub magic_mapping: bool,
+    // Reveals every tile within this radius of the user
+    #[serde(default)]
+    pub clairvoyance: Option<i32>,
+    // Fires ammunition when equipped in the Ranged slot
+    #[serde(default)]
+    pub ranged_weapon: Option<RangedWeaponDef>,
+    // How many shots a stack of this ammunition holds
+    #[serde(default)]
+    pub ammo: Option<i32>,
+    pub spawn: SpawnDef,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TrapDef {
+    pub name: String,
+    pub glyph: String,
+    pub color: (u8, u8, u8),
+    #[serde(default)]
+    pub damage: Option<Scaled>,
+    #[serde(default)]
+    pub status: Option<StatusDef>,
+    #[serde(default)]
+    pub teleport: bool,
+    #[serde(default)]
+    pub alarm: bool,
+    #[serde(default)]
+    pub single_use: bool,
+    pub spawn: SpawnDef,
+}
+
+// Everything that can be spawned, as described by the raw file
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RawMaster {
+    pub monsters: Vec<MonsterDef>,
+    pub items: Vec<ItemDef>,
+    #[serde(default)]
+    pub traps: Vec<TrapDef>,
+}
+
+impl RawMaster {
+    pub fn embedded() -> Result<RawMaster, String> {
+        RawMaster::parse(EMBEDDED_SPAWNS, "built-in spawns")
+    }
+
+    pub fn from_file(path: &str) -> Result<RawMaster, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read raws '{}': {}", path, e))?;
+        RawMaster::parse(&text, path)
+    }
+
+    fn parse(text: &str, source: &str) -> Result<RawMaster, String> {
+        let raws: RawMaster =
+            serde_json::from_str(text).map_err(|e| format!("{}: {}", source, e))?;
+
+        let problems = raws.problems();
+        if problems.is_empty() {
+            Ok(raws)
+        } else {
+            Err(format!("{}: {}", source, problems.join("; ")))
+        }
+    }
+
+    // Every definition that would spawn something broken, named so it can be
+    // found in the file
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut names: Vec<&str> = Vec::new();
+
+        for monster in self.monsters.iter() {
+            let mut problem = |message: &str| {
+                problems.push(format!("monster '{}': {}", monster.name, message));
+            };
+            if names.contains(&monster.name.as_str()) {
+                problem("name is used more than once");
+            }
+            names.push(&monster.name);
+
+            check_look(&monster.glyph, &mut problem);
+            check_spawn(&monster.spawn, &mut problem);
+            if monster.vision < 1 {
+                problem("vision must be at least 1");
+            }
+            let stats = &monster.stats;
+            let all_stats = [
+                stats.level,
+                stats.vitality,
+                stats.strength,
+                stats.agility,
+                stats.magic,
+            ];
+            if all_stats.iter().any(|stat| !stat.is_valid()) {
+                problem("'every' must be at least 1");
+            }
+            if stats.vitality.at(1) < 1 {
+                problem("vitality must be at least 1");
+            }
+            if matches!(monster.ranged, Some(weapon) if weapon.range < 1) {
+                problem("ranged range must be at least 1");
+            }
+            if monster.behaviour == BehaviourKind::Kiter && monster.ranged.is_none() {
+                problem("a Kiter needs a ranged attack");
+            }
+        }
+
+        for item in self.items.iter() {
+            let mut problem = |message: &str| {
+                problems.push(format!("item '{}': {}", item.name, message));
+            };
+            if names.contains(&item.name.as_str()) {
+                problem("name is used more than once");
+            }
+            names.push(&item.name);
+
+            check_look(&item.glyph, &mut problem);
+            check_spawn(&item.spawn, &mut problem);
+
+            let scaled = [
+                item.heal,
+                item.damage,
+                item.status.as_ref().map(|status| status.potency),
+            ];
+            if scaled.iter().flatten().any(|value| !value.is_valid()) {
+                problem("'every' must be at least 1");
+            }
+            if item.heal.is_none()
+                && item.damage.is_none()
+                && item.status.is_none()
+                && !item.food
+                && item.equippable.is_none()
+                && !item.key
+                && !item.magic_mapping
+                && item.clairvoyance.is_none()
+                && item.ammo.is_none()
+            {
+                problem("has no heal, damage, status, food, equippable, key, magic_mapping, clairvoyance or ammo");
+            }
+            if item.damage.is_some() && item.range.is_none() {
+                problem("damage needs a range to pick a target");
+            }
+            if item.area_of_effect.is_some() && item.range.is_none() {
+                problem("area_of_effect needs a range to pick a target");
+            }
+            if matches!(item.range, Some(range) if range < 1) {
+                problem("range must be at least 1");
+            }
+            if matches!(item.area_of_effect, Some(radius) if radius < 1) {
+                problem("area_of_effect must be at least 1");
+            }
+            if matches!(&item.status, Some(status) if status.turns < 1) {
+                problem("status turns must be at least 1");
+            }
+            if matches!(item.clairvoyance, Some(radius) if radius < 1) {
+                problem("clairvoyance must be at least 1");
+            }
+            if (item.magic_mapping || item.clairvoyance.is_some()) && item.range.is_some() {
+                problem("magic_mapping and clairvoyance only work on the user");
+            }
+            if item.ranged_weapon.is_some()
+                && !matches!(&item.equippable, Some(equip) if equip.slot == EquipmentSlot::Ranged)
+            {
+                problem("ranged_weapon must be equippable in the Ranged slot");
+            }
+            if matches!(item.ranged_weapon, Some(weapon) if weapon.range < 1) {
+                problem("ranged_weapon range must be at least 1");
+            }
+            if matches!(item.ammo, Some(count) if count < 1) {
+                problem("ammo must be at least 1");
+            }
+            if item.equippable.is_some() && (item.consumable || item.range.is_some()) {
+                problem("equippable items cannot be consumable or ranged");
+            }
+        }
+
+        for trap in self.traps.iter() {
+            let mut problem = |message: &str| {
+                problems.push(format!("trap '{}': {}", trap.name, message));
+            };
+            if names.contains(&trap.name.as_str()) {
+                problem("name is used more than once");
+            }
+            names.push(&trap.name);
+
+            check_look(&trap.glyph, &mut problem);
+            check_spawn(&trap.spawn, &mut problem);
+
+            let scaled = [
+                trap.damage,
+                trap.status.as_ref().map(|status| status.potency),
+            ];
+            if scaled.iter().flatten().any(|value| !value.is_valid()) {
+                problem("'every' must be at least 1");
+            }
+            if trap.damage.is_none() && trap.status.is_none() && !trap.teleport && !trap.alarm {
+                problem("has no damage, status, teleport or alarm");
+            }
+            if matches!(&trap.status, Some(status) if status.turns < 1) {
+                problem("status turns must be at least 1");
+            }
+        }
+
+        problems
+    }
+
+    pub fn monster_table(&self) -> RandomTable {
+        self.monsters
+            .iter()
+            .fold(RandomTable::new(), |table, monster| {
+                table.add(monster.spawn.table_entry(&monster.name))
+            })
+    }
+
+    pub fn item_table(&self) -> RandomTable {
+        self.items.iter().fold(RandomTable::new(), |table, item| {
+            table.add(item.spawn.table_entry(&item.name))
+        })
+    }
+
+    pub fn trap_table(&self) -> RandomTable {
+        self.traps.iter().fold(RandomTable::new(), |table, trap| {
+            table.add(trap.spawn.table_entry(&trap.name))
+        })
+    }
+
+    pub fn monster(&self, name: &str) -> Option<&MonsterDef> {
+        self.monsters.iter().find(|monster| monster.name == name)
+    }
+
+    pub fn item(&self, name: &str) -> Option<&ItemDef> {
+        self.items.iter().find(|item| item.name == name)
+    }
+
+    pub fn trap(&self, name: &str) -> Option<&TrapDef> {
+        self.traps.iter().find(|trap| trap.name == name)
+    }
+
+    // Chance of each monster, item and trap turning up at every depth down
+    // to `max_depth`, for tuning the spawn weights
+    pub fn spawn_report(&self, max_depth: i32) -> String {
+        let tables = [
+            ("monsters", self.monster_table()),
+            ("items", self.item_table()),
+            ("traps", self.trap_table()),
+        ];
+
+        let mut report = String::new();
+        for depth in 1..=max_depth {
+            report.push_str(&format!("depth {}\n", depth));
+            for (title, table) in tables.iter() {
+                report.push_str(&format!("  {}\n", title));
+                for (name, chance) in table.distribution(depth) {
+                    report.push_str(&format!("    {:5.1}%  {}\n", chance * 100.0, name));
+                }
+            }
+        }
+        report
+    }
+}
+
+fn check_look<F: FnMut(&str)>(glyph: &str, problem: &mut F) {
+    if glyph.chars().count() != 1 {
+        problem("glyph must be a single character");
+    }
+}
+
+fn check_spawn<F: FnMut(&str)>(spawn: &SpawnDef, problem: &mut F) {
+    if spawn.weight < 0 {
+        problem("spawn weight cannot be negative");
+    }
+    if spawn.min_depth < 1 {
+        problem("spawn min_depth must be at least 1");
+    }
+    if matches!(spawn.max_depth, Some(max_depth) if max_depth < spawn.min_depth) {
+        problem("spawn max_depth is below min_depth");
+    }
+}
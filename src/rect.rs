@@ -1,5 +1,6 @@
 use crate::map::Map;
 
+#[derive(Clone)]
 pub struct Rect {
     pub x1: i32,
     pub x2: i32,
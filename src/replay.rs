@@ -0,0 +1,170 @@
+use super::input::{key_from_name, key_name, Input};
+use super::map_builders::BuilderKind;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+// Bump whenever the meaning of recorded inputs changes
+const REPLAY_VERSION: u32 = 2;
+
+// Replays are JSON lines: a header, then one event per line. Writing line by
+// line means a game that panics still leaves a usable replay behind.
+#[derive(Serialize, Deserialize)]
+struct ReplayHeader {
+    version: u32,
+    seed: u64,
+    // None when each level picked its own map builder
+    map_builder: Option<String>,
+}
+
+// `frame` counts the frames on which the game waited for input, so inputs
+// land on the same game state however fast they are played back
+#[derive(Serialize, Deserialize)]
+struct ReplayEvent {
+    frame: u64,
+    key: Option<String>,
+    mouse: (i32, i32),
+    click: bool,
+}
+
+pub struct Recorder {
+    writer: BufWriter<File>,
+    frame: u64,
+}
+
+impl Recorder {
+    pub fn create(
+        path: &str,
+        seed: u64,
+        map_builder: Option<BuilderKind>,
+    ) -> Result<Recorder, String> {
+        let file =
+            File::create(path).map_err(|e| format!("unable to create replay '{}': {}", path, e))?;
+        let mut recorder = Recorder {
+            writer: BufWriter::new(file),
+            frame: 0,
+        };
+        recorder.write_line(&ReplayHeader {
+            version: REPLAY_VERSION,
+            seed,
+            map_builder: map_builder.map(|kind| kind.name().to_string()),
+        });
+        Ok(recorder)
+    }
+
+    // Called once for every frame on which the game waits for input
+    pub fn record(&mut self, input: &Input) {
+        let frame = self.frame;
+        self.frame += 1;
+
+        // Keys without a name are ignored by every screen, so only clicks and
+        // named keys are worth keeping
+        let key = input
+            .key
+            .filter(|key| key_from_name(&key_name(*key)).is_some())
+            .map(key_name);
+        if key.is_none() && !input.left_click {
+            return;
+        }
+
+        self.write_line(&ReplayEvent {
+            frame,
+            key,
+            mouse: input.mouse_pos,
+            click: input.left_click,
+        });
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) {
+        serde_json::to_writer(&mut self.writer, value).expect("Unable to write replay");
+        self.writer
+            .write_all(b"\n")
+            .and_then(|_| self.writer.flush())
+            .expect("Unable to write replay");
+    }
+}
+
+pub struct Playback {
+    pub seed: u64,
+    pub map_builder: Option<BuilderKind>,
+    // Frames advanced per rendered frame
+    pub speed: u32,
+    events: Vec<(u64, Input)>,
+    next_event: usize,
+    frame: u64,
+}
+
+impl Playback {
+    pub fn open(path: &str, speed: u32) -> Result<Playback, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read replay '{}': {}", path, e))?;
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+        let header: ReplayHeader = match lines.next() {
+            None => return Err(format!("replay '{}' is empty", path)),
+            Some(line) => serde_json::from_str(line)
+                .map_err(|e| format!("replay '{}' has a bad header: {}", path, e))?,
+        };
+        if header.version != REPLAY_VERSION {
+            return Err(format!(
+                "replay version {} does not match game version {}",
+                header.version, REPLAY_VERSION
+            ));
+        }
+
+        let map_builder = match &header.map_builder {
+            None => None,
+            Some(name) => Some(
+                name.parse()
+                    .map_err(|e| format!("replay '{}' has a bad header: {}", path, e))?,
+            ),
+        };
+
+        let mut events = Vec::new();
+        for (number, line) in lines.enumerate() {
+            let event: ReplayEvent = serde_json::from_str(line)
+                .map_err(|e| format!("replay event {}: {}", number + 1, e))?;
+            let key = match &event.key {
+                None => None,
+                Some(name) => Some(key_from_name(name).ok_or_else(|| {
+                    format!("replay event {}: unknown key '{}'", number + 1, name)
+                })?),
+            };
+            events.push((
+                event.frame,
+                Input {
+                    key,
+                    mouse_pos: event.mouse,
+                    left_click: event.click,
+                },
+            ));
+        }
+
+        Ok(Playback {
+            seed: header.seed,
+            map_builder,
+            speed: u32::max(1, speed),
+            events,
+            next_event: 0,
+            frame: 0,
+        })
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next_event >= self.events.len()
+    }
+
+    // Called once for every frame on which the game waits for input
+    pub fn next_input(&mut self) -> Input {
+        let frame = self.frame;
+        self.frame += 1;
+
+        match self.events.get(self.next_event) {
+            Some((event_frame, input)) if *event_frame == frame => {
+                self.next_event += 1;
+                *input
+            }
+            _ => Input::default(),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use super::components::*;
+use super::{GameLog, Map, RunSeed, RunState};
+use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{
+    DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+use std::path::Path;
+
+const SAVE_PATH: &str = "./savegame.json";
+
+// Bump whenever the set or shape of saved components changes
+const SAVE_VERSION: u32 = 16;
+
+#[derive(Serialize, Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+macro_rules! serialize_individually {
+    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        SerializeComponents::<NoError, SimpleMarker<SerializeMe>>::serialize(
+            &( $ecs.read_storage::<$type>(), ),
+            &$data.0,
+            &$data.1,
+            &mut $ser,
+        )
+        .expect("Unable to serialize component");
+        )*
+    };
+}
+
+macro_rules! deserialize_individually {
+    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        DeserializeComponents::<NoError, _>::deserialize(
+            &mut ( &mut $ecs.write_storage::<$type>(), ),
+            &$data.0,
+            &mut $data.1,
+            &mut $data.2,
+            &mut $de,
+        )
+        .map_err(|e| e.to_string())?;
+        )*
+    };
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_game(_ecs: &mut World, _runstate: RunState) {}
+
+// Writes the world out along with the run state to resume it in, which
+// falls back to waiting for input for menus and target selection
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_game(ecs: &mut World, runstate: RunState) {
+    let map_copy = (*ecs.fetch::<Map>()).clone();
+    let log_copy = ecs.fetch::<GameLog>().entries.clone();
+    let seed = ecs.fetch::<RunSeed>().seed;
+
+    // The generator's state cannot be written out, so the rolls go on from
+    // a new seed drawn from it, kept in the save. A resumed game then makes
+    // the same rolls the saved one would have made from here.
+    let rng_seed = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let rng_seed = rng.next_u64();
+        *rng = RandomNumberGenerator::seeded(rng_seed);
+        rng_seed
+    };
+
+    let helper = ecs
+        .create_entity()
+        .with(SerializationHelper {
+            map: map_copy,
+            log: log_copy,
+            seed,
+            rng_seed,
+            runstate: runstate.resumable(),
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    {
+        let data = (
+            ecs.entities(),
+            ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+        );
+
+        let writer = File::create(SAVE_PATH).expect("Unable to create save file");
+        let mut serializer = serde_json::Serializer::new(writer);
+        SaveHeader {
+            version: SAVE_VERSION,
+        }
+        .serialize(&mut serializer)
+        .expect("Unable to serialize save header");
+        serialize_individually!(
+            ecs,
+            serializer,
+            data,
+            Ammunition,
+            AreaOfEffect,
+            Behaviour,
+            BlocksTile,
+            BlocksVisibility,
+            Clairvoyance,
+            CombatStats,
+            Consumable,
+            DefenseBonus,
+            Door,
+            EntityMoved,
+            EntryTrigger,
+            Equippable,
+            Equipped,
+            HealEffect,
+            Hidden,
+            HungerClock,
+            InBackpack,
+            InflictsDamage,
+            InflictsStatus,
+            Item,
+            Key,
+            MagicMapper,
+            MeleePowerBonus,
+            Monster,
+            Name,
+            Player,
+            PlayerMemory,
+            Position,
+            ProvidesFood,
+            RaisesAlarm,
+            Ranged,
+            RangedWeapon,
+            Renderable,
+            SingleActivation,
+            StatusEffects,
+            SufferDamage,
+            TeleportsTarget,
+            Viewshed,
+            WantsToDisplayContent,
+            WantsToDropItem,
+            WantsToMelee,
+            WantsToOpenDoor,
+            WantsToPickupItem,
+            WantsToRemoveItem,
+            WantsToShoot,
+            WantsToUseItem,
+            SerializationHelper
+        );
+    }
+
+    ecs.delete_entity(helper)
+        .expect("Unable to delete save helper");
+}
+
+pub fn does_save_exist() -> bool {
+    Path::new(SAVE_PATH).exists()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_game(_ecs: &mut World) -> Result<RunState, String> {
+    Err("Saving is not supported in the browser".to_string())
+}
+
+// Reads every saved entity into `ecs`, failing on the first part of the
+// file that does not parse
+#[cfg(not(target_arch = "wasm32"))]
+fn read_save(ecs: &mut World, text: &str) -> Result<(), String> {
+    let mut de = serde_json::Deserializer::from_str(text);
+
+    let header = SaveHeader::deserialize(&mut de).map_err(|e| e.to_string())?;
+    if header.version != SAVE_VERSION {
+        return Err(format!(
+            "save file version {} does not match game version {}",
+            header.version, SAVE_VERSION
+        ));
+    }
+
+    {
+        let mut data = (
+            &mut ecs.entities(),
+            &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(),
+            &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
+        );
+        deserialize_individually!(
+            ecs,
+            de,
+            data,
+            Ammunition,
+            AreaOfEffect,
+            Behaviour,
+            BlocksTile,
+            BlocksVisibility,
+            Clairvoyance,
+            CombatStats,
+            Consumable,
+            DefenseBonus,
+            Door,
+            EntityMoved,
+            EntryTrigger,
+            Equippable,
+            Equipped,
+            HealEffect,
+            Hidden,
+            HungerClock,
+            InBackpack,
+            InflictsDamage,
+            InflictsStatus,
+            Item,
+            Key,
+            MagicMapper,
+            MeleePowerBonus,
+            Monster,
+            Name,
+            Player,
+            PlayerMemory,
+            Position,
+            ProvidesFood,
+            RaisesAlarm,
+            Ranged,
+            RangedWeapon,
+            Renderable,
+            SingleActivation,
+            StatusEffects,
+            SufferDamage,
+            TeleportsTarget,
+            Viewshed,
+            WantsToDisplayContent,
+            WantsToDropItem,
+            WantsToMelee,
+            WantsToOpenDoor,
+            WantsToPickupItem,
+            WantsToRemoveItem,
+            WantsToShoot,
+            WantsToUseItem,
+            SerializationHelper
+        );
+    }
+    de.end().map_err(|e| e.to_string())?;
+
+    if (&ecs.read_storage::<SerializationHelper>())
+        .join()
+        .next()
+        .is_none()
+    {
+        return Err("save file is missing the map".to_string());
+    }
+    Ok(())
+}
+
+// Replaces the world with the saved one and returns the run state it was
+// saved in
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_game(ecs: &mut World) -> Result<RunState, String> {
+    let data = fs::read_to_string(SAVE_PATH).map_err(|e| e.to_string())?;
+
+    // Read the whole file into a scratch world first, so a stale, truncated
+    // or corrupt save leaves the current game intact
+    let mut scratch = World::new();
+    super::register_components(&mut scratch);
+    scratch.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+    read_save(&mut scratch, &data)?;
+
+    ecs.delete_all();
+    read_save(ecs, &data)?;
+
+    let mut helper_entity: Option<Entity> = None;
+    let mut runstate = RunState::AwaitingInput;
+    {
+        let entities = ecs.entities();
+        let helpers = ecs.read_storage::<SerializationHelper>();
+        let players = ecs.read_storage::<Player>();
+        let positions = ecs.read_storage::<Position>();
+
+        for (entity, helper) in (&entities, &helpers).join() {
+            let mut map = ecs.write_resource::<Map>();
+            *map = helper.map.clone();
+            map.reset_content();
+
+            let mut gamelog = ecs.write_resource::<GameLog>();
+            gamelog.entries = helper.log.clone();
+
+            let mut run_seed = ecs.write_resource::<RunSeed>();
+            run_seed.seed = helper.seed;
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            *rng = RandomNumberGenerator::seeded(helper.rng_seed);
+            runstate = helper.runstate;
+
+            helper_entity = Some(entity);
+        }
+
+        for (entity, _player, pos) in (&entities, &players, &positions).join() {
+            let mut player_pos = ecs.write_resource::<Point>();
+            *player_pos = Point::new(pos.x, pos.y);
+            let mut player_entity = ecs.write_resource::<Entity>();
+            *player_entity = entity;
+        }
+    }
+
+    match helper_entity {
+        None => Err("save file is missing the map".to_string()),
+        Some(helper) => {
+            ecs.delete_entity(helper)
+                .expect("Unable to delete save helper");
+            Ok(runstate)
+        }
+    }
+}
+
+pub fn delete_save() {
+    if does_save_exist() {
+        std::fs::remove_file(SAVE_PATH).expect("Unable to delete save file");
+    }
+}
@@ -0,0 +1,6 @@
+// Seed of the current run. Every random roll in the game comes from the
+// RandomNumberGenerator resource seeded with it, so the same seed and the
+// same inputs replay the same game.
+pub struct RunSeed {
+    pub seed: u64,
+}
@@ -0,0 +1,101 @@
+use super::{GameLog, Monster, RunState, StatusEffects, StatusKind, SufferDamage};
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        Entities<'a>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, SufferDamage>,
+        WriteExpect<'a, GameLog>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, runstate, entities, mut statuses, mut suffer_damage, mut gamelog) =
+            data;
+
+        // Effects wear off once per turn, and a turn ends with the monsters
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        let mut recovered: Vec<Entity> = Vec::new();
+        for (entity, statuses) in (&entities, &mut statuses).join() {
+            for effect in statuses.effects.iter_mut() {
+                if effect.kind == StatusKind::Poison {
+                    SufferDamage::new_damage(&mut suffer_damage, entity, effect.potency);
+                    if entity == *player_entity {
+                        gamelog
+                            .entries
+                            .push(format!("You suffer {} damage from poison.", effect.potency));
+                    }
+                }
+
+                effect.turns -= 1;
+                if effect.turns <= 0 && entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push(format!("You are no longer {}.", effect.kind.adjective()));
+                }
+            }
+
+            statuses.effects.retain(|effect| effect.turns > 0);
+            if statuses.effects.is_empty() {
+                recovered.push(entity);
+            }
+        }
+
+        for entity in recovered {
+            statuses.remove(entity);
+        }
+    }
+}
+
+pub fn has_status(ecs: &World, entity: Entity, kind: StatusKind) -> bool {
+    let statuses = ecs.read_storage::<StatusEffects>();
+    match statuses.get(entity) {
+        Some(statuses) => statuses.has(kind),
+        None => false,
+    }
+}
+
+// A hasted player gets every other action for free, before the monsters move
+pub fn player_extra_action(ecs: &mut World) -> bool {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut statuses = ecs.write_storage::<StatusEffects>();
+    match statuses.get_mut(player_entity) {
+        Some(statuses) if statuses.has(StatusKind::Haste) => {
+            statuses.extra_action_taken = !statuses.extra_action_taken;
+            statuses.extra_action_taken
+        }
+        _ => false,
+    }
+}
+
+// Hasted monsters get a second action every turn, once the first has been
+// dealt with, so there is another pass to make if any are left standing
+pub fn monster_extra_actions(ecs: &World) -> bool {
+    let monsters = ecs.read_storage::<Monster>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    (&monsters, &statuses)
+        .join()
+        .any(|(_monster, statuses)| statuses.has(StatusKind::Haste))
+}
+
+// Where a confused creature ends up going, whatever it meant to do
+pub fn stumble_direction(rng: &mut RandomNumberGenerator) -> (i32, i32) {
+    match rng.roll_dice(1, 8) {
+        1 => (-1, 0),
+        2 => (1, 0),
+        3 => (0, -1),
+        4 => (0, 1),
+        5 => (-1, -1),
+        6 => (1, -1),
+        7 => (-1, 1),
+        _ => (1, 1),
+    }
+}
@@ -0,0 +1,187 @@
+use super::{
+    CombatStats, EntityMoved, EntryTrigger, GameLog, Hidden, InflictsDamage, InflictsStatus, Map,
+    Monster, Name, PlayerMemory, Point, Position, RaisesAlarm, SingleActivation, StatusEffects,
+    StatusKind, SufferDamage, TeleportsTarget, TileType, Viewshed, MONSTER_MEMORY_TURNS,
+    TRAP_CONCEALMENT,
+};
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+
+// Whether a character notices a hidden trap: a d20 plus their agility, and
+// any bonus for looking on purpose, has to beat how well it is concealed
+pub fn perception_roll(rng: &mut RandomNumberGenerator, stats: &CombatStats, bonus: i32) -> bool {
+    rng.roll_dice(1, 20) + stats.agility + bonus > TRAP_CONCEALMENT
+}
+
+pub struct TriggerSystem {}
+
+impl<'a> System<'a> for TriggerSystem {
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, Point>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        Entities<'a>,
+        WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+        WriteStorage<'a, Hidden>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, StatusEffects>,
+        ReadStorage<'a, EntryTrigger>,
+        ReadStorage<'a, InflictsDamage>,
+        ReadStorage<'a, InflictsStatus>,
+        ReadStorage<'a, TeleportsTarget>,
+        ReadStorage<'a, RaisesAlarm>,
+        ReadStorage<'a, SingleActivation>,
+        ReadStorage<'a, Monster>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, PlayerMemory>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            mut player_pos,
+            player_entity,
+            mut gamelog,
+            mut rng,
+            entities,
+            mut entity_moved,
+            mut positions,
+            mut viewsheds,
+            mut hidden,
+            mut suffer_damage,
+            mut statuses,
+            entry_triggers,
+            inflicts_damage,
+            inflicts_status,
+            teleports,
+            alarms,
+            single_activation,
+            monsters,
+            names,
+            mut memories,
+        ) = data;
+
+        let moved: Vec<(Entity, i32, i32)> = (&entities, &entity_moved, &positions)
+            .join()
+            .map(|(entity, _, pos)| (entity, pos.x, pos.y))
+            .collect();
+        entity_moved.clear();
+
+        let mut spent: Vec<Entity> = Vec::new();
+        for (mover, x, y) in moved {
+            let is_player = mover == *player_entity;
+            let traps: Vec<Entity> = map.tile_content[x as usize][y as usize]
+                .iter()
+                .filter(|trap| **trap != mover && entry_triggers.contains(**trap))
+                .filter(|trap| !spent.contains(trap))
+                .copied()
+                .collect();
+
+            for trap in traps {
+                let trap_name = &names.get(trap).unwrap().name;
+
+                // Setting a trap off in plain view gives it away
+                if is_player || map.visible_tiles[x as usize][y as usize] {
+                    hidden.remove(trap);
+                    if is_player {
+                        gamelog
+                            .entries
+                            .push(format!("You trigger a {}!", trap_name));
+                    } else if let Some(name) = names.get(mover) {
+                        gamelog
+                            .entries
+                            .push(format!("The {} triggers a {}!", name.name, trap_name));
+                    }
+                }
+
+                if let Some(damage) = inflicts_damage.get(trap) {
+                    SufferDamage::new_damage(&mut suffer_damage, mover, damage.damage);
+                    if is_player {
+                        gamelog
+                            .entries
+                            .push(format!("You take {} damage.", damage.damage));
+                    }
+                }
+
+                if let Some(inflicts) = inflicts_status.get(trap) {
+                    StatusEffects::apply(&mut statuses, mover, inflicts.effect);
+                    if is_player {
+                        gamelog
+                            .entries
+                            .push(format!("You are {}.", inflicts.effect.kind.adjective()));
+                    }
+                }
+
+                if teleports.contains(trap) {
+                    let destination = random_open_floor(&map, &mut rng);
+                    if let (Some((new_x, new_y)), Some(pos)) =
+                        (destination, positions.get_mut(mover))
+                    {
+                        pos.x = new_x;
+                        pos.y = new_y;
+                        if is_player {
+                            player_pos.x = new_x;
+                            player_pos.y = new_y;
+                            gamelog
+                                .entries
+                                .push("The world lurches around you.".to_string());
+                        }
+                        if let Some(viewshed) = viewsheds.get_mut(mover) {
+                            viewshed.dirty = true;
+                        }
+                    }
+                }
+
+                // An alarm wakes every monster on the level and sends them
+                // to see what set it off
+                if alarms.contains(trap) {
+                    for (_monster, status) in (&monsters, &mut statuses).join() {
+                        status.effects.retain(|e| e.kind != StatusKind::Sleep);
+                    }
+                    for (monster, _) in (&entities, &monsters).join() {
+                        memories
+                            .insert(
+                                monster,
+                                PlayerMemory {
+                                    last_seen: Some(Point::new(x, y)),
+                                    turns: MONSTER_MEMORY_TURNS,
+                                },
+                            )
+                            .expect("Unable to insert memory");
+                    }
+                    gamelog.entries.push("An alarm blares!".to_string());
+                }
+
+                if single_activation.contains(trap) {
+                    spent.push(trap);
+                }
+            }
+        }
+
+        for trap in spent {
+            entities.delete(trap).expect("Delete failed");
+        }
+    }
+}
+
+// A floor tile nothing is standing on, anywhere on the level
+fn random_open_floor(map: &Map, rng: &mut RandomNumberGenerator) -> Option<(i32, i32)> {
+    let mut open = Vec::new();
+    for x in 0..map.width {
+        for y in 0..map.height {
+            if map.tiles[x as usize][y as usize] == TileType::Floor
+                && !map.blocked[x as usize][y as usize]
+            {
+                open.push((x, y));
+            }
+        }
+    }
+    if open.is_empty() {
+        return None;
+    }
+    Some(open[rng.range(0, open.len() as i32) as usize])
+}
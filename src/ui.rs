@@ -1,24 +1,57 @@
+use super::camera::Camera;
+use super::input::Input;
+use super::saveload_system;
 use super::{
-    CombatStats, GameLog, InBackpack, Name, Player, State, Viewshed, BASE_BG_COLOR, CYAN_COLOR,
-    EXPBAR_OFFSET, EXP_OFFSET, GREEN_COLOR, HEALTHBAR_OFFSET, HEALTH_OFFSET, LOG_OFFSET, MAP_X,
-    MAP_Y, ORANGE_COLOR, PURPLE_COLOR, RED_COLOR, WHITE_COLOR, YELLOW_COLOR,
+    Ammunition, AreaOfEffect, Clairvoyance, CombatStats, DefenseBonus, Door, Equipped, GameLog,
+    HealEffect, Hidden, HungerClock, InBackpack, InflictsDamage, InflictsStatus, Key, MagicMapper,
+    Map, MeleePowerBonus, Name, Player, ProvidesFood, RaisesAlarm, Ranged, RangedWeapon,
+    Renderable, RunSeed, RunState, State, StatusEffects, TeleportsTarget, TileType, Viewshed,
+    BASE_BG_COLOR, BLUE_COLOR, CYAN_COLOR, DEPTH_OFFSET, EXPBAR_OFFSET, EXP_OFFSET, GREEN_COLOR,
+    HEALTHBAR_OFFSET, HEALTH_OFFSET, HUNGER_OFFSET, LOG_OFFSET, ORANGE_COLOR, PURPLE_COLOR,
+    RED_COLOR, STATUS_OFFSET, VIEW_X, VIEW_Y, WHITE_COLOR, YELLOW_COLOR,
 };
 use bracket_lib::prelude::*;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 
 pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
-    let log_size = 50 - MAP_Y - 2;
-    // Build box starting from bottom of map space (MAP_Y coordinate), spanning
-    // full width of viewport (MAP_X)
+    let log_size = 50 - VIEW_Y - 2;
+    // Build box starting from bottom of map space (VIEW_Y coordinate), spanning
+    // full width of viewport (VIEW_X)
     ctx.draw_box(
         0,
-        MAP_Y,
-        MAP_X - 1,
+        VIEW_Y,
+        VIEW_X - 1,
         log_size + 1,
         RGB::named(WHITE_COLOR),
         RGB::named(BASE_BG_COLOR),
     );
 
+    let map = ecs.fetch::<Map>();
+    let depth_message = format!(" Depth: {}", map.depth);
+    ctx.print_color(
+        DEPTH_OFFSET,
+        VIEW_Y,
+        RGB::named(YELLOW_COLOR),
+        RGB::named(BASE_BG_COLOR),
+        &depth_message,
+    );
+
+    // Running effects sit between the depth and the health bar
+    let player_entity = ecs.fetch::<Entity>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    if let Some(statuses) = statuses.get(*player_entity) {
+        for (i, effect) in statuses.effects.iter().enumerate() {
+            ctx.print_color(
+                STATUS_OFFSET + 4 * i as i32,
+                VIEW_Y,
+                RGB::named(effect.kind.color()),
+                RGB::named(BASE_BG_COLOR),
+                effect.kind.code(),
+            );
+        }
+    }
+
     let combat_stats = ecs.read_storage::<CombatStats>();
     let players = ecs.read_storage::<Player>();
     for (_player, stats) in (&players, &combat_stats).join() {
@@ -31,7 +64,7 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
         let hp_message = format!(" HP: {}/{}", stats.hp, stats.max_hp);
         ctx.print_color(
             HEALTH_OFFSET,
-            MAP_Y,
+            VIEW_Y,
             RGB::named(health_color),
             RGB::named(BASE_BG_COLOR),
             &hp_message,
@@ -39,7 +72,7 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
 
         ctx.draw_bar_horizontal(
             HEALTHBAR_OFFSET,
-            MAP_Y,
+            VIEW_Y,
             10,
             stats.hp,
             stats.max_hp,
@@ -50,7 +83,7 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
         let exp_message = format!(" EXP: {}/{}", stats.exp, stats.level * 100);
         ctx.print_color(
             EXP_OFFSET,
-            MAP_Y,
+            VIEW_Y,
             RGB::named(PURPLE_COLOR),
             RGB::named(BASE_BG_COLOR),
             &exp_message,
@@ -58,7 +91,7 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
 
         ctx.draw_bar_horizontal(
             EXPBAR_OFFSET,
-            MAP_Y,
+            VIEW_Y,
             10,
             stats.exp,
             stats.level * 100,
@@ -67,9 +100,21 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
         );
     }
 
+    // Hunger sits on the bottom edge of the box, under the log
+    let hunger_clocks = ecs.read_storage::<HungerClock>();
+    if let Some(clock) = hunger_clocks.get(*player_entity) {
+        ctx.print_color(
+            HUNGER_OFFSET,
+            VIEW_Y + log_size + 1,
+            RGB::named(clock.state.color()),
+            RGB::named(BASE_BG_COLOR),
+            format!(" {} ", clock.state.label()),
+        );
+    }
+
     let log = ecs.fetch::<GameLog>();
     for (i, message) in log.entries.iter().rev().take(log_size as usize).enumerate() {
-        ctx.print(LOG_OFFSET, MAP_Y + i as i32 + 1, message);
+        ctx.print(LOG_OFFSET, VIEW_Y + i as i32 + 1, message);
     }
 }
 
@@ -86,28 +131,68 @@ pub enum CharacterMenuResult {
     NoResponse,
 }
 
-pub fn show_inventory(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Option<Entity>) {
-    show_item_menu(gs, ctx, "Inventory")
+pub fn show_inventory(gs: &mut State, input: &Input) -> (ItemMenuResult, Option<Entity>) {
+    show_item_menu(&usable_items(&gs.ecs), input)
+}
+
+pub fn show_drop_menu(gs: &mut State, input: &Input) -> (ItemMenuResult, Option<Entity>) {
+    show_item_menu(&player_inventory(&gs.ecs), input)
 }
 
-pub fn show_drop_menu(gs: &mut State, ctx: &mut BTerm) -> (ItemMenuResult, Option<Entity>) {
-    show_item_menu(gs, ctx, "Choose item to drop:")
+fn player_inventory(ecs: &World) -> Vec<(Entity, String)> {
+    let player_entity = ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let ammunition = ecs.read_storage::<Ammunition>();
+    let entities = ecs.entities();
+
+    (&entities, &backpack, &names)
+        .join()
+        .filter(|item| item.1.owner == *player_entity)
+        .map(|(entity, _pack, name)| match ammunition.get(entity) {
+            Some(ammo) => (entity, format!("{} ({})", name.name, ammo.count)),
+            None => (entity, name.name.to_string()),
+        })
+        .collect()
 }
 
-fn show_item_menu<S: ToString>(
-    gs: &mut State,
-    ctx: &mut BTerm,
-    title: S,
-) -> (ItemMenuResult, Option<Entity>) {
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
-    let entities = gs.ecs.entities();
-
-    let inventory = (&backpack, &names)
+// Equipped items are out of the backpack, but are still listed in the
+// inventory so they can be taken off
+fn player_equipment(ecs: &World) -> Vec<(Entity, String)> {
+    let player_entity = ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let entities = ecs.entities();
+
+    (&entities, &equipped, &names)
         .join()
-        .filter(|item| item.0.owner == *player_entity);
-    let count = inventory.count();
+        .filter(|item| item.1.owner == *player_entity)
+        .map(|(entity, _equipped, name)| (entity, format!("{} (equipped)", name.name)))
+        .collect()
+}
+
+// What the inventory offers to use or take off. Ammunition is only ever
+// fired from a bow, so it is left to the drop menu.
+fn usable_items(ecs: &World) -> Vec<(Entity, String)> {
+    let ammunition = ecs.read_storage::<Ammunition>();
+    let mut items: Vec<(Entity, String)> = player_inventory(ecs)
+        .into_iter()
+        .filter(|(item, _name)| !ammunition.contains(*item))
+        .collect();
+    items.extend(player_equipment(ecs));
+    items
+}
+
+pub fn draw_inventory(ecs: &World, ctx: &mut BTerm) {
+    draw_item_menu(ctx, "Inventory", &usable_items(ecs));
+}
+
+pub fn draw_drop_menu(ecs: &World, ctx: &mut BTerm) {
+    draw_item_menu(ctx, "Choose item to drop:", &player_inventory(ecs));
+}
+
+fn draw_item_menu<S: ToString>(ctx: &mut BTerm, title: S, items: &[(Entity, String)]) {
+    let count = items.len();
 
     // For now, the list of items should be small.
     // TODO: once the player is able to collect more items, make this pageable
@@ -135,12 +220,7 @@ fn show_item_menu<S: ToString>(
         "Esc to close",
     );
 
-    let mut equippable: Vec<Entity> = Vec::new();
-    for (j, (entity, _pack, name)) in (&entities, &backpack, &names)
-        .join()
-        .filter(|item| item.1.owner == *player_entity)
-        .enumerate()
-    {
+    for (j, (_entity, name)) in items.iter().enumerate() {
         ctx.set(
             17,
             y + j as i32,
@@ -163,21 +243,19 @@ fn show_item_menu<S: ToString>(
             to_cp437(')'),
         );
 
-        ctx.print(21, y + j as i32, &name.name.to_string());
-        equippable.push(entity);
+        ctx.print(21, y + j as i32, name);
     }
+}
 
-    match ctx.key {
+fn show_item_menu(items: &[(Entity, String)], input: &Input) -> (ItemMenuResult, Option<Entity>) {
+    match input.key {
         None => (ItemMenuResult::NoResponse, None),
         Some(key) => match key {
             VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
             _ => {
                 let selection = letter_to_option(key);
-                if selection > -1 && selection < count as i32 {
-                    return (
-                        ItemMenuResult::Selected,
-                        Some(equippable[selection as usize]),
-                    );
+                if selection > -1 && selection < items.len() as i32 {
+                    return (ItemMenuResult::Selected, Some(items[selection as usize].0));
                 }
 
                 (ItemMenuResult::NoResponse, None)
@@ -186,12 +264,27 @@ fn show_item_menu<S: ToString>(
     }
 }
 
-pub fn show_character(gs: &mut State, ctx: &mut BTerm) -> CharacterMenuResult {
-    let players = gs.ecs.read_storage::<Player>();
-    let combat_stats = gs.ecs.read_storage::<CombatStats>();
-    let names = gs.ecs.read_storage::<Name>();
+pub fn draw_character(ecs: &World, ctx: &mut BTerm) {
+    let players = ecs.read_storage::<Player>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let names = ecs.read_storage::<Name>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let power_bonuses = ecs.read_storage::<MeleePowerBonus>();
+    let defense_bonuses = ecs.read_storage::<DefenseBonus>();
+    let player_entity = ecs.fetch::<Entity>();
+
+    let power_bonus: i32 = (&power_bonuses, &equipped)
+        .join()
+        .filter(|(_bonus, equipped_by)| equipped_by.owner == *player_entity)
+        .map(|(bonus, _equipped_by)| bonus.power)
+        .sum();
+    let defense_bonus: i32 = (&defense_bonuses, &equipped)
+        .join()
+        .filter(|(_bonus, equipped_by)| equipped_by.owner == *player_entity)
+        .map(|(bonus, _equipped_by)| bonus.defense)
+        .sum();
 
-    let stat_count: i32 = 6;
+    let stat_count: i32 = 9;
     let y = (25 - (stat_count / 2)) as i32;
 
     ctx.draw_box(
@@ -223,9 +316,13 @@ pub fn show_character(gs: &mut State, ctx: &mut BTerm) -> CharacterMenuResult {
         ctx.print(17, y + 3, format!("Agility {}", combat_stat.agility));
         ctx.print(17, y + 4, format!("Vitality {}", combat_stat.vitality));
         ctx.print(17, y + 5, format!("Magic {}", combat_stat.magic));
+        ctx.print(17, y + 7, format!("Power bonus {}", power_bonus));
+        ctx.print(17, y + 8, format!("Defense bonus {}", defense_bonus));
     }
+}
 
-    match ctx.key {
+pub fn show_character(_gs: &mut State, input: &Input) -> CharacterMenuResult {
+    match input.key {
         None => CharacterMenuResult::NoResponse,
         Some(key) => match key {
             VirtualKeyCode::Escape => CharacterMenuResult::Cancel,
@@ -234,15 +331,194 @@ pub fn show_character(gs: &mut State, ctx: &mut BTerm) -> CharacterMenuResult {
     }
 }
 
-pub fn ranged_target(
-    gs: &mut State,
-    ctx: &mut BTerm,
-    range: i32,
-) -> (ItemMenuResult, Option<Point>) {
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let player_pos = gs.ecs.fetch::<Point>();
-    let viewsheds = gs.ecs.read_storage::<Viewshed>();
+// Visible cells within range of the player, or None if the player cannot see
+fn targetable_cells(ecs: &World, range: i32) -> Option<Vec<Point>> {
+    let player_entity = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+
+    viewsheds.get(*player_entity).map(|visible| {
+        visible
+            .visible_tiles
+            .iter()
+            .filter(|point| {
+                DistanceAlg::Pythagoras.distance2d(*player_pos, **point) <= range as f32
+            })
+            .copied()
+            .collect()
+    })
+}
+
+// What the player can tell about an entity at a glance: how hurt and
+// afflicted a creature is, or what an item or a spotted trap does
+fn describe(ecs: &World, entity: Entity) -> Vec<String> {
+    let mut details = Vec::new();
+
+    if let Some(stats) = ecs.read_storage::<CombatStats>().get(entity) {
+        details.push(format!("{}/{} hp", stats.hp, stats.max_hp));
+    }
+    if let Some(statuses) = ecs.read_storage::<StatusEffects>().get(entity) {
+        for effect in statuses.effects.iter() {
+            details.push(effect.kind.adjective().to_string());
+        }
+    }
+
+    if let Some(door) = ecs.read_storage::<Door>().get(entity) {
+        let state = match (door.open, door.locked) {
+            (true, _) => "open",
+            (false, true) => "locked",
+            (false, false) => "closed",
+        };
+        details.push(state.to_string());
+    }
+
+    if let Some(heal) = ecs.read_storage::<HealEffect>().get(entity) {
+        details.push(format!("heals {} hp", heal.amount));
+    }
+    if let Some(damage) = ecs.read_storage::<InflictsDamage>().get(entity) {
+        details.push(format!("deals {} damage", damage.damage));
+    }
+    if let Some(inflicts) = ecs.read_storage::<InflictsStatus>().get(entity) {
+        details.push(format!(
+            "{} for {} turns",
+            inflicts.effect.kind.adjective(),
+            inflicts.effect.turns
+        ));
+    }
+    if let Some(ranged) = ecs.read_storage::<Ranged>().get(entity) {
+        details.push(format!("range {}", ranged.range));
+    }
+    if let Some(area) = ecs.read_storage::<AreaOfEffect>().get(entity) {
+        details.push(format!("blast radius {}", area.radius));
+    }
+    if ecs.read_storage::<ProvidesFood>().contains(entity) {
+        details.push("staves off hunger".to_string());
+    }
+    if ecs.read_storage::<Key>().contains(entity) {
+        details.push("opens a locked door".to_string());
+    }
+    if ecs.read_storage::<MagicMapper>().contains(entity) {
+        details.push("reveals the level".to_string());
+    }
+    if let Some(clairvoyance) = ecs.read_storage::<Clairvoyance>().get(entity) {
+        details.push(format!("reveals the map within {}", clairvoyance.radius));
+    }
+    if ecs.read_storage::<TeleportsTarget>().contains(entity) {
+        details.push("teleports".to_string());
+    }
+    if ecs.read_storage::<RaisesAlarm>().contains(entity) {
+        details.push("raises the alarm".to_string());
+    }
+    if let Some(weapon) = ecs.read_storage::<RangedWeapon>().get(entity) {
+        details.push(format!(
+            "shoots {} tiles, {} power",
+            weapon.range, weapon.power
+        ));
+    }
+    if let Some(ammo) = ecs.read_storage::<Ammunition>().get(entity) {
+        details.push(format!("{} left", ammo.count));
+    }
+    if let Some(bonus) = ecs.read_storage::<MeleePowerBonus>().get(entity) {
+        details.push(format!("+{} power", bonus.power));
+    }
+    if let Some(bonus) = ecs.read_storage::<DefenseBonus>().get(entity) {
+        details.push(format!("+{} defense", bonus.defense));
+    }
+
+    details
+}
+
+// Names everything the player can see on the tile under the mouse, each
+// followed by its description, in a box beside the mouse that is kept
+// inside the 80x50 console
+pub fn draw_tooltips(ecs: &World, ctx: &mut BTerm) {
+    let mouse_pos = ctx.mouse_pos();
+    let target = match Camera::of_player(ecs).to_world(mouse_pos) {
+        Some(target) => target,
+        None => return,
+    };
+
+    let map = ecs.fetch::<Map>();
+    if target.x < 0 || target.x >= map.width || target.y < 0 || target.y >= map.height {
+        return;
+    }
+    let (x, y) = (target.x as usize, target.y as usize);
+    if !map.visible_tiles[x][y] {
+        return;
+    }
+
+    let names = ecs.read_storage::<Name>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let mut lines: Vec<(String, RGB)> = Vec::new();
+    for entity in map.tile_content[x][y].iter() {
+        if hidden.contains(*entity) {
+            continue;
+        }
+        if let Some(name) = names.get(*entity) {
+            let color = renderables
+                .get(*entity)
+                .map_or(RGB::named(WHITE_COLOR), |render| render.fg);
+            lines.push((name.name.to_string(), color));
+            for detail in describe(ecs, *entity) {
+                lines.push((format!(" {}", detail), RGB::named(WHITE_COLOR)));
+            }
+        }
+    }
+    if map.tiles[x][y] == TileType::DownStairs {
+        lines.push(("Stairs down".to_string(), RGB::named(CYAN_COLOR)));
+    }
+    if lines.is_empty() {
+        return;
+    }
+
+    // Right of the mouse if the box fits there, otherwise left of it
+    let width = lines.iter().map(|(line, _)| line.len()).max().unwrap_or(0) as i32 + 1;
+    let height = lines.len() as i32 + 1;
+    let mut box_x = mouse_pos.0 + 1;
+    if box_x + width > 79 {
+        box_x = mouse_pos.0 - width - 1;
+    }
+    let box_x = i32::max(0, box_x);
+    let box_y = i32::clamp(mouse_pos.1, 0, i32::max(0, 49 - height));
+
+    ctx.draw_box(
+        box_x,
+        box_y,
+        width,
+        height,
+        RGB::named(WHITE_COLOR),
+        RGB::named(BASE_BG_COLOR),
+    );
+    for (i, (line, color)) in lines.iter().enumerate() {
+        ctx.print_color(
+            box_x + 1,
+            box_y + 1 + i as i32,
+            *color,
+            RGB::named(BASE_BG_COLOR),
+            line,
+        );
+    }
+}
+
+// Tints the background of a map tile, if the camera has it in view
+fn highlight(ctx: &mut BTerm, camera: &Camera, point: Point, color: (u8, u8, u8)) {
+    if let Some((x, y)) = camera.to_screen(point.x, point.y) {
+        ctx.set_bg(x, y, RGB::named(color));
+    }
+}
+
+// The way the player is travelling along, up to where they clicked
+pub fn draw_travel_path(ecs: &World, ctx: &mut BTerm, path: &[usize]) {
+    let map = ecs.fetch::<Map>();
+    let camera = Camera::of_player(ecs);
+    for idx in path.iter() {
+        let (x, y) = map.idx_xy(*idx);
+        highlight(ctx, &camera, Point::new(x, y), BLUE_COLOR);
+    }
+}
 
+pub fn draw_ranged_target(ecs: &World, ctx: &mut BTerm, range: i32, item: Entity) {
     ctx.print_color(
         5,
         0,
@@ -252,41 +528,185 @@ pub fn ranged_target(
     );
 
     // Highlight target cells
-    let mut available_cells = Vec::new();
-    let visible = viewsheds.get(*player_entity);
-    if let Some(visible) = visible {
-        for point in visible.visible_tiles.iter() {
-            let distance = DistanceAlg::Pythagoras.distance2d(*player_pos, *point);
-            if distance <= range as f32 {
-                ctx.set_bg(point.x, point.y, RGB::named(YELLOW_COLOR));
-                available_cells.push(point);
-            }
-        }
-    } else {
-        return (ItemMenuResult::Cancel, None);
+    let camera = Camera::of_player(ecs);
+    let available_cells = targetable_cells(ecs, range).unwrap_or_default();
+    for point in available_cells.iter() {
+        highlight(ctx, &camera, *point, YELLOW_COLOR);
     }
 
     let mouse_pos = ctx.mouse_pos();
-    let mut valid_target = false;
-    for pos in available_cells.iter() {
-        if pos.x == mouse_pos.0 && pos.y == mouse_pos.1 {
-            valid_target = true;
+    let target = camera
+        .to_world(mouse_pos)
+        .filter(|target| available_cells.contains(target));
+    if let Some(target) = target {
+        // Preview what a blast would catch before committing to it
+        let area_of_effect = ecs.read_storage::<AreaOfEffect>();
+        if let Some(area) = area_of_effect.get(item) {
+            let map = ecs.fetch::<Map>();
+            for tile in map.blast_area(target, area.radius).iter() {
+                highlight(ctx, &camera, *tile, ORANGE_COLOR);
+            }
         }
-    }
-    if valid_target {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(GREEN_COLOR));
-        if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(Point::new(mouse_pos.0, mouse_pos.1)),
-            );
-        }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(RED_COLOR));
-        if ctx.left_click {
-            return (ItemMenuResult::Cancel, None);
+    }
+}
+
+pub fn ranged_target(gs: &mut State, input: &Input, range: i32) -> (ItemMenuResult, Option<Point>) {
+    let available_cells = match targetable_cells(&gs.ecs, range) {
+        Some(cells) => cells,
+        None => return (ItemMenuResult::Cancel, None),
+    };
+
+    if !input.left_click {
+        return (ItemMenuResult::NoResponse, None);
+    }
+
+    let target = Camera::of_player(&gs.ecs)
+        .to_world(input.mouse_pos)
+        .filter(|target| available_cells.contains(target));
+    match target {
+        Some(target) => (ItemMenuResult::Selected, Some(target)),
+        None => (ItemMenuResult::Cancel, None),
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum MainMenuSelection {
+    NewGame,
+    Continue,
+    Quit,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MainMenuResult {
+    NoSelection { selected: MainMenuSelection },
+    Selected { selected: MainMenuSelection },
+}
+
+fn main_menu_options() -> Vec<MainMenuSelection> {
+    let mut options = vec![MainMenuSelection::NewGame];
+    if saveload_system::does_save_exist() {
+        options.push(MainMenuSelection::Continue);
+    }
+    options.push(MainMenuSelection::Quit);
+    options
+}
+
+fn main_menu_selection(ecs: &World, options: &[MainMenuSelection]) -> MainMenuSelection {
+    match *ecs.fetch::<RunState>() {
+        RunState::MainMenu { menu_selection } if options.contains(&menu_selection) => {
+            menu_selection
         }
+        _ => MainMenuSelection::NewGame,
+    }
+}
+
+pub fn draw_main_menu(ecs: &World, ctx: &mut BTerm) {
+    let options = main_menu_options();
+    let selection = main_menu_selection(ecs, &options);
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(YELLOW_COLOR),
+        RGB::named(BASE_BG_COLOR),
+        "Explore",
+    );
+
+    for (i, option) in options.iter().enumerate() {
+        let label = match option {
+            MainMenuSelection::NewGame => "New Game",
+            MainMenuSelection::Continue => "Continue",
+            MainMenuSelection::Quit => "Quit",
+        };
+        let fg = if *option == selection {
+            ORANGE_COLOR
+        } else {
+            WHITE_COLOR
+        };
+        ctx.print_color_centered(
+            24 + i as i32,
+            RGB::named(fg),
+            RGB::named(BASE_BG_COLOR),
+            label,
+        );
+    }
+}
+
+pub fn main_menu(gs: &mut State, input: &Input) -> MainMenuResult {
+    let options = main_menu_options();
+    let selection = main_menu_selection(&gs.ecs, &options);
+    let current = options
+        .iter()
+        .position(|option| *option == selection)
+        .unwrap_or(0);
+
+    match input.key {
+        None => MainMenuResult::NoSelection {
+            selected: selection,
+        },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => MainMenuResult::NoSelection {
+                selected: MainMenuSelection::Quit,
+            },
+            VirtualKeyCode::Up | VirtualKeyCode::K | VirtualKeyCode::Numpad8 => {
+                MainMenuResult::NoSelection {
+                    selected: options[(current + options.len() - 1) % options.len()],
+                }
+            }
+            VirtualKeyCode::Down | VirtualKeyCode::J | VirtualKeyCode::Numpad2 => {
+                MainMenuResult::NoSelection {
+                    selected: options[(current + 1) % options.len()],
+                }
+            }
+            VirtualKeyCode::Return => MainMenuResult::Selected {
+                selected: selection,
+            },
+            _ => MainMenuResult::NoSelection {
+                selected: selection,
+            },
+        },
     }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum GameOverResult {
+    NoSelection,
+    Quit,
+}
+
+pub fn draw_game_over(ecs: &World, ctx: &mut BTerm) {
+    let seed = ecs.fetch::<RunSeed>();
+    let map = ecs.fetch::<Map>();
+
+    ctx.draw_box(
+        15,
+        18,
+        49,
+        8,
+        RGB::named(WHITE_COLOR),
+        RGB::named(BASE_BG_COLOR),
+    );
+    ctx.print_color_centered(
+        20,
+        RGB::named(RED_COLOR),
+        RGB::named(BASE_BG_COLOR),
+        "You are dead!",
+    );
+    ctx.print_centered(22, format!("You reached depth {}.", map.depth));
+    ctx.print_centered(23, format!("Run seed: {}", seed.seed));
+    ctx.print_color_centered(
+        25,
+        RGB::named(YELLOW_COLOR),
+        RGB::named(BASE_BG_COLOR),
+        "Press Escape to quit",
+    );
+}
 
-    (ItemMenuResult::NoResponse, None)
+pub fn game_over(_gs: &mut State, input: &Input) -> GameOverResult {
+    match input.key {
+        Some(VirtualKeyCode::Escape) => GameOverResult::Quit,
+        _ => GameOverResult::NoSelection,
+    }
 }
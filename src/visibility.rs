@@ -1,4 +1,5 @@
-use super::{Map, Player};
+use super::trap_system::perception_roll;
+use super::{CombatStats, GameLog, Hidden, Map, Name, Player};
 use super::{Position, Viewshed};
 use bracket_lib::prelude::*;
 use specs::prelude::*;
@@ -12,10 +13,26 @@ impl<'a> System<'a> for VisibilitySystem {
         WriteStorage<'a, Viewshed>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Player>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Name>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameLog>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player) = data;
+        let (
+            mut map,
+            entities,
+            mut viewshed,
+            pos,
+            player,
+            mut hidden,
+            combat_stats,
+            names,
+            mut rng,
+            mut gamelog,
+        ) = data;
 
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             if viewshed.dirty {
@@ -38,6 +55,21 @@ impl<'a> System<'a> for VisibilitySystem {
                     for vis in viewshed.visible_tiles.iter() {
                         map.revealed_tiles[vis.x as usize][vis.y as usize] = true;
                         map.visible_tiles[vis.x as usize][vis.y as usize] = true;
+
+                        // Each fresh look at a tile is a chance to notice
+                        // what is hidden there
+                        let stats = match combat_stats.get(ent) {
+                            Some(stats) => stats,
+                            None => continue,
+                        };
+                        for e in map.tile_content[vis.x as usize][vis.y as usize].iter() {
+                            if hidden.contains(*e) && perception_roll(&mut rng, stats, 0) {
+                                hidden.remove(*e);
+                                if let Some(name) = names.get(*e) {
+                                    gamelog.entries.push(format!("You spot a {}.", name.name));
+                                }
+                            }
+                        }
                     }
                 }
             }